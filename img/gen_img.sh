@@ -3,5 +3,9 @@ cd "$(dirname $0)"
 mkdir -p gen/
 convert \( src/earth_albedo.png -resize 3600x1800 \) \( src/earth_bathymetry.jpg -channel B -separate -negate \) -compose CopyOpacity -composite gen/earth.png
 convert src/starmap.exr -gamma 1.25 gen/starmap_2020_8k.png
+convert src/earth_clouds.jpg -colorspace Gray -resize 3600x1800 gen/earth_clouds.png
+convert src/earth_night.jpg -resize 3600x1800 gen/earth_night.png
 convert src/moon_albedo.tif -resize 3600x1800 gen/moon.png
+# Lowest point black, highest white, as the sim example expects
+convert src/moon_height.tif -auto-level -resize 3600x1800 gen/moon_height.png
 python to_cube.py gen/starmap_2020_8k.png 1024 gen/milky_way.png
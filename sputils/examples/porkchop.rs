@@ -0,0 +1,33 @@
+use sputils::eph::{Body, JPL};
+use sputils::porkchop::{Porkchop, PorkchopOptions, Quantity};
+use sputils::time::TDB;
+
+fn main() {
+    let mut jpl = JPL::new().unwrap();
+    // 2020 Earth-Mars window
+    let dep = (TDB(2400000.5, 58970.0), TDB(2400000.5, 59090.0), 121);
+    let arr = (TDB(2400000.5, 59130.0), TDB(2400000.5, 59400.0), 136);
+    let p = Porkchop::compute(
+        &mut jpl,
+        Body::Earth,
+        Body::Mars,
+        dep,
+        arr,
+        &PorkchopOptions::default(),
+    )
+    .unwrap();
+
+    if let Some((i, j)) = p.minimum() {
+        let k = j * p.width() + i;
+        println!(
+            "Best: depart {:.1} arrive {:.1} C3 {:.3} km2/s2",
+            p.departure[i].0 + p.departure[i].1,
+            p.arrival[j].0 + p.arrival[j].1,
+            p.c3[k]
+        );
+    }
+    println!("C3 range: {:?}", p.range(Quantity::C3));
+
+    let f = std::fs::File::create("porkchop.csv").unwrap();
+    p.write_csv(std::io::BufWriter::new(f)).unwrap();
+}
@@ -0,0 +1,263 @@
+// Rise/set, twilight, lunar phase and season instants
+// Searched in TT, reported in UTC
+// UT1 is taken as UTC (|DUT1| < 0.9 s is well below refraction uncertainty)
+// Positions are geometric, light-time and aberration are ignored except for the Sun's longitude
+// Epochs outside the ephemeris panic
+use crate::coord::{Observer, GCRS};
+use crate::eclipse::{R_MOON, R_SUN};
+use crate::eph::{Body, JPL};
+use crate::search;
+use crate::time::{TimeError, TAI, TDB, TT, UT1, UTC};
+use sofa_sys::*;
+use std::convert::TryFrom;
+use std::f64::consts::{PI, TAU};
+
+// Instants are refined to about a second
+const TOL_DAYS: f64 = 1.0 / 86400.0;
+// Sampling for rise/set, shorter than any time a body spends above or below the horizon
+// except close to the polar day/night transition
+const STEP_RISE_SET: f64 = 1.0 / 48.0;
+/// Standard horizontal refraction (radians)
+pub const REFRACTION: f64 = 34.0 / 60.0 * DD2R;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Horizon {
+    Rise,
+    Set,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Twilight {
+    Civil,
+    Nautical,
+    Astronomical,
+}
+
+impl Twilight {
+    /// Altitude of the Sun's centre bounding this twilight (radians)
+    pub fn altitude(self) -> f64 {
+        match self {
+            Self::Civil => -6.0 * DD2R,
+            Self::Nautical => -12.0 * DD2R,
+            Self::Astronomical => -18.0 * DD2R,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    MarchEquinox,
+    JuneSolstice,
+    SeptemberEquinox,
+    DecemberSolstice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoonPhase {
+    New,
+    FirstQuarter,
+    Full,
+    LastQuarter,
+}
+
+pub fn tt_to_utc(tt: TT) -> Result<UTC, TimeError> {
+    UTC::try_from(TAI::from(tt))
+}
+
+fn tt_to_ut1(tt: TT) -> Result<UT1, TimeError> {
+    tt_to_utc(tt)?.try_into_ut1(0.0)
+}
+
+fn tt_to_tdb(tt: TT) -> TDB {
+    // TDB-TT is under 2 ms
+    TDB::from_tt(tt, 0.0)
+}
+
+fn span(start: TT, end: TT) -> f64 {
+    (end.0 - start.0) + (end.1 - start.1)
+}
+
+fn at(start: TT, x: f64) -> TT {
+    TT(start.0, start.1 + x)
+}
+
+// Geocentric position (km) and topocentric altitude (radians)
+fn altitude(jpl: &mut JPL, obs: &Observer, body: Body, tt: TT) -> (na::Vector3<f64>, f64) {
+    let p = jpl
+        .position(tt_to_tdb(tt), body, Body::Earth)
+        .expect("Epoch outside the ephemeris");
+    let alt = tt_to_ut1(tt).map_or(f64::NAN, |ut1| {
+        obs.alt_az(&GCRS(p), &GCRS::gcrs_to_itrs_mat(&tt, &ut1, 0.0, 0.0))
+            .0
+    });
+    (p, alt)
+}
+
+fn crossings<F>(start: TT, end: TT, mut f: F) -> Result<Vec<(UTC, Horizon)>, TimeError>
+where
+    F: FnMut(TT) -> f64,
+{
+    // Fail early rather than returning NaN from the search
+    tt_to_utc(start)?;
+    tt_to_utc(end)?;
+    search::roots(
+        |x| f(at(start, x)),
+        0.0,
+        span(start, end),
+        STEP_RISE_SET,
+        TOL_DAYS,
+    )
+    .into_iter()
+    .map(|(x, rising)| {
+        Ok((
+            tt_to_utc(at(start, x))?,
+            if rising { Horizon::Rise } else { Horizon::Set },
+        ))
+    })
+    .collect()
+}
+
+/// Sunrise and sunset (upper limb on the horizon, standard refraction)
+pub fn sun_rise_set(
+    jpl: &mut JPL,
+    obs: &Observer,
+    start: TT,
+    end: TT,
+) -> Result<Vec<(UTC, Horizon)>, TimeError> {
+    crossings(start, end, |tt| {
+        let (p, alt) = altitude(jpl, obs, Body::Sun, tt);
+        alt + REFRACTION + (R_SUN / p.norm()).asin()
+    })
+}
+
+/// Beginning (`Rise`, dawn) and end (`Set`, dusk) of twilight
+pub fn twilight(
+    jpl: &mut JPL,
+    obs: &Observer,
+    kind: Twilight,
+    start: TT,
+    end: TT,
+) -> Result<Vec<(UTC, Horizon)>, TimeError> {
+    crossings(start, end, |tt| {
+        altitude(jpl, obs, Body::Sun, tt).1 - kind.altitude()
+    })
+}
+
+/// Moonrise and moonset (upper limb on the horizon, standard refraction, topocentric)
+pub fn moon_rise_set(
+    jpl: &mut JPL,
+    obs: &Observer,
+    start: TT,
+    end: TT,
+) -> Result<Vec<(UTC, Horizon)>, TimeError> {
+    crossings(start, end, |tt| {
+        let (p, alt) = altitude(jpl, obs, Body::Moon, tt);
+        alt + REFRACTION + (R_MOON / p.norm()).asin()
+    })
+}
+
+/// Sun-Moon-Earth angle (radians), 0 at full moon
+pub fn moon_phase_angle(jpl: &mut JPL, tt: TT) -> f64 {
+    let t = tt_to_tdb(tt);
+    let s = jpl
+        .position(t, Body::Sun, Body::Earth)
+        .expect("Epoch outside the ephemeris");
+    let m = jpl
+        .position(t, Body::Moon, Body::Earth)
+        .expect("Epoch outside the ephemeris");
+    (s - m).angle(&-m)
+}
+
+/// Illuminated fraction of the lunar disk
+pub fn moon_illuminated_fraction(jpl: &mut JPL, tt: TT) -> f64 {
+    (1.0 + moon_phase_angle(jpl, tt).cos()) / 2.0
+}
+
+// Apparent ecliptic longitude of date (radians)
+fn ecliptic_longitude(jpl: &mut JPL, body: Body, tt: TT) -> f64 {
+    let p = jpl
+        .position(tt_to_tdb(tt), body, Body::Earth)
+        .expect("Epoch outside the ephemeris");
+    let mut rm = [[0.0_f64; 3]; 3];
+    let mut dpsi = 0.0;
+    let mut deps = 0.0;
+    unsafe {
+        iauEcm06(tt.0, tt.1, rm.as_mut_ptr());
+        iauNut06a(tt.0, tt.1, &mut dpsi, &mut deps);
+    }
+    let e = unsafe { crate::sofa_matrix(&rm) } * p;
+    let mut lon = e.y.atan2(e.x) + dpsi;
+    if body == Body::Sun {
+        // Annual aberration
+        lon -= 20.4898 * DAS2R * jpl.au() / p.norm();
+    }
+    lon
+}
+
+// Instants where `f` (an increasing angle) passes through multiples of a quarter turn
+fn quarters<F>(start: TT, end: TT, step: f64, mut f: F) -> Result<Vec<(UTC, usize)>, TimeError>
+where
+    F: FnMut(TT) -> f64,
+{
+    tt_to_utc(start)?;
+    tt_to_utc(end)?;
+    let mut v = Vec::new();
+    for q in 0..4 {
+        let target = q as f64 * PI / 2.0;
+        // Wrapped to -PI..PI, the falling edge is the discontinuity opposite the target
+        let roots = search::roots(
+            |x| (f(at(start, x)) - target + PI).rem_euclid(TAU) - PI,
+            0.0,
+            span(start, end),
+            step,
+            TOL_DAYS,
+        );
+        for (x, rising) in roots {
+            if rising {
+                v.push((x, q));
+            }
+        }
+    }
+    v.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    v.into_iter()
+        .map(|(x, q)| Ok((tt_to_utc(at(start, x))?, q)))
+        .collect()
+}
+
+/// Equinoxes and solstices, from the Sun's apparent longitude
+pub fn seasons(jpl: &mut JPL, start: TT, end: TT) -> Result<Vec<(UTC, Season)>, TimeError> {
+    let v = quarters(start, end, 1.0, |tt| ecliptic_longitude(jpl, Body::Sun, tt))?;
+    Ok(v.into_iter()
+        .map(|(t, q)| {
+            (
+                t,
+                match q {
+                    0 => Season::MarchEquinox,
+                    1 => Season::JuneSolstice,
+                    2 => Season::SeptemberEquinox,
+                    _ => Season::DecemberSolstice,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Principal lunar phases, from the Moon-Sun difference in apparent longitude
+pub fn moon_phases(jpl: &mut JPL, start: TT, end: TT) -> Result<Vec<(UTC, MoonPhase)>, TimeError> {
+    let v = quarters(start, end, 0.5, |tt| {
+        ecliptic_longitude(jpl, Body::Moon, tt) - ecliptic_longitude(jpl, Body::Sun, tt)
+    })?;
+    Ok(v.into_iter()
+        .map(|(t, q)| {
+            (
+                t,
+                match q {
+                    0 => MoonPhase::New,
+                    1 => MoonPhase::FirstQuarter,
+                    2 => MoonPhase::Full,
+                    _ => MoonPhase::LastQuarter,
+                },
+            )
+        })
+        .collect())
+}
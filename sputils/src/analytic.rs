@@ -0,0 +1,103 @@
+// Low precision positions for when no JPL ephemeris is available
+// Planets from Simon et al. (1994) mean elements (SOFA iauPlan94), arcminutes over 1800-2050
+// Earth and Sun from the SOFA fit to VSOP2000 (iauEpv00), a few km over 1900-2100
+// Moon from the Astronomical Almanac's truncated lunar series, 0.3 degrees
+use crate::bodies;
+use crate::coord::GCRS;
+use crate::eph::Body;
+use crate::time::TDB;
+use sofa_sys::*;
+
+// SOFA's astronomical unit (km), what its series are in. A JPL ephemeris has its own, see
+// `crate::eph::JPL::au`
+const AU: f64 = DAU / 1e3;
+
+/// Position (km) of `target` relative to `center`, ICRF axes.
+///
+/// Same as `crate::eph::JPL::position` but much less accurate, `None` when Pluto is involved.
+pub fn position(t: TDB, target: Body, center: Body) -> Option<na::Vector3<f64>> {
+    Some(barycentric(t, target)? - barycentric(t, center)?)
+}
+
+/// Position (km) of the Moon relative to the Earth, ICRF axes
+pub fn moon(t: TDB) -> na::Vector3<f64> {
+    let c = centuries(t);
+    let sin = |deg: f64| (deg * DD2R).sin();
+    let cos = |deg: f64| (deg * DD2R).cos();
+    // Mean equinox and ecliptic of date (degrees)
+    let lon = 218.32 + 481_267.881 * c + 6.29 * sin(135.0 + 477_198.87 * c)
+        - 1.27 * sin(259.3 - 413_335.36 * c)
+        + 0.66 * sin(235.7 + 890_534.22 * c)
+        + 0.21 * sin(269.9 + 954_397.74 * c)
+        - 0.19 * sin(357.5 + 35_999.05 * c)
+        - 0.11 * sin(186.5 + 966_404.03 * c);
+    let lat = 5.13 * sin(93.3 + 483_202.02 * c) + 0.28 * sin(228.2 + 960_400.89 * c)
+        - 0.28 * sin(318.3 + 6_003.15 * c)
+        - 0.17 * sin(217.6 - 407_332.21 * c);
+    // Horizontal parallax
+    let hp = 0.9508
+        + 0.0518 * cos(135.0 + 477_198.87 * c)
+        + 0.0095 * cos(259.3 - 413_335.36 * c)
+        + 0.0078 * cos(235.7 + 890_534.22 * c)
+        + 0.0028 * cos(269.9 + 954_397.74 * c);
+    let r = bodies::EARTH.r_equatorial / sin(hp);
+    let ecl = na::Vector3::new(cos(lat) * cos(lon), cos(lat) * sin(lon), sin(lat)) * r;
+
+    // Transpose, ecliptic of date to ICRS
+    GCRS::gcrs_to_ecliptic_mat(&t.into_tt(0.0)).transpose() * ecl
+}
+
+/// Position (km) relative to the solar system barycentre, ICRF axes
+pub fn barycentric(t: TDB, b: Body) -> Option<na::Vector3<f64>> {
+    let (helio, bary) = earth(t);
+    let sun = bary - helio;
+    Some(match b {
+        Body::SSB => na::Vector3::zeros(),
+        Body::Sun => sun,
+        Body::Earth => bary,
+        Body::Moon => bary + moon(t),
+        Body::EMB => bary + moon(t) * (bodies::MOON.gm / (bodies::EARTH.gm + bodies::MOON.gm)),
+        Body::Pluto => return None,
+        Body::Mercury
+        | Body::Venus
+        | Body::Mars
+        | Body::Jupiter
+        | Body::Saturn
+        | Body::Uranus
+        | Body::Neptune => sun + heliocentric(t, b),
+    })
+}
+
+// Heliocentric planet (km), Mercury to Neptune with 3 for the EMB
+fn heliocentric(t: TDB, b: Body) -> na::Vector3<f64> {
+    let np = match b {
+        Body::Mercury => 1,
+        Body::Venus => 2,
+        Body::EMB => 3,
+        Body::Mars => 4,
+        Body::Jupiter => 5,
+        Body::Saturn => 6,
+        Body::Uranus => 7,
+        _ => 8,
+    };
+    let mut pv = [[0.0_f64; 3]; 2];
+    // Only warns outside 1000-3000, or fails to converge on a nonsensical date
+    unsafe { iauPlan94(t.0, t.1, np, pv.as_mut_ptr()) };
+    na::Vector3::from_column_slice(&pv[0]) * AU
+}
+
+// Heliocentric and barycentric Earth (km)
+fn earth(t: TDB) -> (na::Vector3<f64>, na::Vector3<f64>) {
+    let mut pvh = [[0.0_f64; 3]; 2];
+    let mut pvb = [[0.0_f64; 3]; 2];
+    // Only warns outside 1900-2100
+    unsafe { iauEpv00(t.0, t.1, pvh.as_mut_ptr(), pvb.as_mut_ptr()) };
+    (
+        na::Vector3::from_column_slice(&pvh[0]) * AU,
+        na::Vector3::from_column_slice(&pvb[0]) * AU,
+    )
+}
+
+fn centuries(t: TDB) -> f64 {
+    ((t.0 - DJ00) + t.1) / DJC
+}
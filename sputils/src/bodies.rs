@@ -0,0 +1,311 @@
+// Physical constants of solar system bodies
+// GM from DE430 (Folkner et al. 2014) and satellite ephemerides, radii from IAU WGCCRE 2015,
+// J2 from the latest gravity field solutions, rotation rates from `crate::orient` (IAU degrees per day)
+// Earth uses WGS84/IERS values to match `crate::coord::Observer`
+use crate::eph::{Body, Satellite};
+use sofa_sys::{DAYSEC, DD2R};
+
+/// Atmosphere parameters for single scattering rendering
+#[derive(Debug, Clone, Copy)]
+pub struct Atmosphere {
+    /// Height of the top of the atmosphere above the equatorial surface (km)
+    pub height: f64,
+    /// Surface pressure (Pa)
+    pub surface_pressure: f64,
+    /// Rayleigh density scale height (m)
+    pub rayleigh_scale_height: f64,
+    /// Rayleigh scattering coefficients at sea level, red/green/blue (1/m)
+    pub rayleigh_scattering: [f64; 3],
+    /// Mie (aerosol) density scale height (m)
+    pub mie_scale_height: f64,
+    /// Mie scattering coefficient at sea level (1/m)
+    pub mie_scattering: f64,
+    /// Mie phase function asymmetry factor
+    pub mie_asymmetry: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Constants {
+    pub name: &'static str,
+    /// Gravitational parameter (km^3/s^2)
+    pub gm: f64,
+    /// Equatorial radius (km)
+    pub r_equatorial: f64,
+    /// Polar radius (km)
+    pub r_polar: f64,
+    /// Unnormalized second zonal harmonic, 0 where unknown
+    pub j2: f64,
+    /// Sidereal rotation rate (rad/s), negative for retrograde rotators
+    pub rotation_rate: f64,
+    pub atmosphere: Option<Atmosphere>,
+}
+
+impl Constants {
+    /// (a - c) / a
+    pub fn flattening(&self) -> f64 {
+        (self.r_equatorial - self.r_polar) / self.r_equatorial
+    }
+
+    /// Radius of the sphere with the same volume (km)
+    pub fn r_mean(&self) -> f64 {
+        (self.r_equatorial * self.r_equatorial * self.r_polar).cbrt()
+    }
+
+    /// Sidereal rotation period (s)
+    pub fn rotation_period(&self) -> f64 {
+        std::f64::consts::TAU / self.rotation_rate.abs()
+    }
+}
+
+pub const SUN: Constants = Constants {
+    name: "Sun",
+    gm: 1.327_124_400_419_39e11,
+    r_equatorial: 695_700.0,
+    r_polar: 695_700.0,
+    j2: 2.2e-7,
+    rotation_rate: 14.1844000 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const MERCURY: Constants = Constants {
+    name: "Mercury",
+    gm: 22_031.78,
+    r_equatorial: 2_440.53,
+    r_polar: 2_438.26,
+    j2: 5.03e-5,
+    rotation_rate: 6.1385108 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+// Cloud tops, the atmosphere is not modelled
+pub const VENUS: Constants = Constants {
+    name: "Venus",
+    gm: 324_858.592,
+    r_equatorial: 6_051.8,
+    r_polar: 6_051.8,
+    j2: 4.458e-6,
+    rotation_rate: -1.4813688 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const EARTH: Constants = Constants {
+    name: "Earth",
+    gm: 398_600.435_436,
+    r_equatorial: 6_378.137,
+    r_polar: 6_356.752,
+    j2: 1.082_626_68e-3,
+    rotation_rate: 7.292_115e-5,
+    atmosphere: Some(Atmosphere {
+        height: 60.0,
+        surface_pressure: 101_325.0,
+        rayleigh_scale_height: 7_994.0,
+        rayleigh_scattering: [3.8e-6, 13.5e-6, 33.1e-6],
+        mie_scale_height: 1_200.0,
+        mie_scattering: 21e-6,
+        mie_asymmetry: 0.76,
+    }),
+};
+
+pub const MOON: Constants = Constants {
+    name: "Moon",
+    gm: 4_902.800_066,
+    r_equatorial: 1_738.1,
+    r_polar: 1_736.0,
+    j2: 2.033e-4,
+    rotation_rate: 13.17635815 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const MARS: Constants = Constants {
+    name: "Mars",
+    gm: 42_828.375_214,
+    r_equatorial: 3_396.19,
+    r_polar: 3_376.20,
+    j2: 1.960_45e-3,
+    rotation_rate: 350.89198226 * DD2R / DAYSEC,
+    // Collienne et al. (2013), dust loading varies a lot
+    atmosphere: Some(Atmosphere {
+        height: 80.0,
+        surface_pressure: 610.0,
+        rayleigh_scale_height: 11_100.0,
+        rayleigh_scattering: [19.918e-6, 13.57e-6, 5.75e-6],
+        mie_scale_height: 11_100.0,
+        mie_scattering: 4e-6,
+        mie_asymmetry: 0.63,
+    }),
+};
+
+pub const JUPITER: Constants = Constants {
+    name: "Jupiter",
+    gm: 126_712_764.8,
+    r_equatorial: 71_492.0,
+    r_polar: 66_854.0,
+    j2: 1.469_65e-2,
+    rotation_rate: 870.5360000 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const SATURN: Constants = Constants {
+    name: "Saturn",
+    gm: 37_940_585.2,
+    r_equatorial: 60_268.0,
+    r_polar: 54_364.0,
+    j2: 1.629_06e-2,
+    rotation_rate: 810.7939024 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const URANUS: Constants = Constants {
+    name: "Uranus",
+    gm: 5_794_548.6,
+    r_equatorial: 25_559.0,
+    r_polar: 24_973.0,
+    j2: 3.510_68e-3,
+    rotation_rate: -501.1600928 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const NEPTUNE: Constants = Constants {
+    name: "Neptune",
+    gm: 6_836_527.1,
+    r_equatorial: 24_764.0,
+    r_polar: 24_341.0,
+    j2: 3.408_4e-3,
+    rotation_rate: 541.1397757 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const PLUTO: Constants = Constants {
+    name: "Pluto",
+    gm: 869.6,
+    r_equatorial: 1_188.3,
+    r_polar: 1_188.3,
+    j2: 0.0,
+    rotation_rate: -56.3625225 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+// Triaxial satellites use the longest equatorial and the polar axis
+
+pub const PHOBOS: Constants = Constants {
+    name: "Phobos",
+    gm: 7.087e-4,
+    r_equatorial: 13.0,
+    r_polar: 9.1,
+    j2: 0.105,
+    rotation_rate: 1128.8445850 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const DEIMOS: Constants = Constants {
+    name: "Deimos",
+    gm: 9.62e-5,
+    r_equatorial: 7.8,
+    r_polar: 5.1,
+    j2: 0.0,
+    rotation_rate: 285.1618970 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const IO: Constants = Constants {
+    name: "Io",
+    gm: 5_959.916,
+    r_equatorial: 1_829.4,
+    r_polar: 1_815.7,
+    j2: 1.8459e-3,
+    rotation_rate: 203.4889538 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const EUROPA: Constants = Constants {
+    name: "Europa",
+    gm: 3_202.739,
+    r_equatorial: 1_562.6,
+    r_polar: 1_559.5,
+    j2: 4.355e-4,
+    rotation_rate: 101.3747235 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const GANYMEDE: Constants = Constants {
+    name: "Ganymede",
+    gm: 9_887.834,
+    r_equatorial: 2_631.2,
+    r_polar: 2_631.2,
+    j2: 1.2733e-4,
+    rotation_rate: 50.3176081 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const CALLISTO: Constants = Constants {
+    name: "Callisto",
+    gm: 7_179.289,
+    r_equatorial: 2_410.3,
+    r_polar: 2_410.3,
+    j2: 3.27e-5,
+    rotation_rate: 21.5710715 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+// Surface, the thick haze is not modelled
+pub const TITAN: Constants = Constants {
+    name: "Titan",
+    gm: 8_978.14,
+    r_equatorial: 2_575.15,
+    r_polar: 2_574.47,
+    j2: 3.1808e-5,
+    rotation_rate: 22.5769768 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const TRITON: Constants = Constants {
+    name: "Triton",
+    gm: 1_427.6,
+    r_equatorial: 1_352.6,
+    r_polar: 1_352.6,
+    j2: 0.0,
+    rotation_rate: -61.2572637 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+pub const CHARON: Constants = Constants {
+    name: "Charon",
+    gm: 105.88,
+    r_equatorial: 606.0,
+    r_polar: 606.0,
+    j2: 0.0,
+    rotation_rate: -56.3625225 * DD2R / DAYSEC,
+    atmosphere: None,
+};
+
+/// Constants of a body, `None` for barycentres
+pub fn body(b: Body) -> Option<&'static Constants> {
+    Some(match b {
+        Body::Sun => &SUN,
+        Body::Mercury => &MERCURY,
+        Body::Venus => &VENUS,
+        Body::Earth => &EARTH,
+        Body::Moon => &MOON,
+        Body::Mars => &MARS,
+        Body::Jupiter => &JUPITER,
+        Body::Saturn => &SATURN,
+        Body::Uranus => &URANUS,
+        Body::Neptune => &NEPTUNE,
+        Body::Pluto => &PLUTO,
+        Body::SSB | Body::EMB => return None,
+    })
+}
+
+pub fn satellite(s: Satellite) -> &'static Constants {
+    match s {
+        Satellite::Phobos => &PHOBOS,
+        Satellite::Deimos => &DEIMOS,
+        Satellite::Io => &IO,
+        Satellite::Europa => &EUROPA,
+        Satellite::Ganymede => &GANYMEDE,
+        Satellite::Callisto => &CALLISTO,
+        Satellite::Titan => &TITAN,
+        Satellite::Triton => &TRITON,
+        Satellite::Charon => &CHARON,
+    }
+}
@@ -67,3 +67,97 @@ impl TEME {
         }
     }
 }
+
+impl GCRS {
+    // IAU 2006/2000A, CIO based
+    pub fn gcrs_to_itrs_mat(
+        tt: &crate::time::TT,
+        ut1: &crate::time::UT1,
+        xp: f64,
+        yp: f64,
+    ) -> na::Matrix3<f64> {
+        let mut rc2t = [[0.0_f64; 3]; 3];
+        unsafe {
+            iauC2t06a(tt.0, tt.1, ut1.0, ut1.1, xp, yp, rc2t.as_mut_ptr());
+            crate::sofa_matrix(&rc2t)
+        }
+    }
+
+    /// IAU 2006 precession and frame bias, to the mean equator and equinox of date
+    pub fn gcrs_to_mean_mat(tt: &crate::time::TT) -> na::Matrix3<f64> {
+        let mut rbp = [[0.0_f64; 3]; 3];
+        unsafe {
+            iauPmat06(tt.0, tt.1, rbp.as_mut_ptr());
+            crate::sofa_matrix(&rbp)
+        }
+    }
+
+    /// IAU 2006 precession and frame bias, to the mean ecliptic and equinox of date
+    pub fn gcrs_to_ecliptic_mat(tt: &crate::time::TT) -> na::Matrix3<f64> {
+        let mut rm = [[0.0_f64; 3]; 3];
+        unsafe {
+            iauEcm06(tt.0, tt.1, rm.as_mut_ptr());
+            crate::sofa_matrix(&rm)
+        }
+    }
+}
+
+impl ICRS {
+    /// Galactic coordinates as defined for Hipparcos, the same as SOFA iauIcrs2g
+    pub fn icrs_to_galactic_mat() -> na::Matrix3<f64> {
+        na::Matrix3::new(
+            -0.054875560416215368492398900454,
+            -0.873437090234885048760383168409,
+            -0.483835015548713226831774175116,
+            0.494109427875583673525222371358,
+            -0.444829629960011178146614061616,
+            0.746982244497218890527388004556,
+            -0.867666149019004701181616534570,
+            -0.198076373431201528180486091412,
+            0.455983776175066922272100478348,
+        )
+    }
+}
+
+/// Observer on the WGS84 ellipsoid
+#[derive(Debug, Clone, Copy)]
+pub struct Observer {
+    /// Geodetic longitude (radians, east positive)
+    pub lon: f64,
+    /// Geodetic latitude (radians)
+    pub lat: f64,
+    /// Height above the ellipsoid (m)
+    pub height: f64,
+}
+
+impl Observer {
+    /// Position in km
+    pub fn itrs(&self) -> ITRS {
+        let mut xyz = [0.0_f64; 3];
+        unsafe {
+            iauGd2gc(
+                WGS84 as _,
+                self.lon,
+                self.lat,
+                self.height,
+                xyz.as_mut_ptr(),
+            );
+        }
+        ITRS(na::Vector3::from_column_slice(&xyz) / 1000.0)
+    }
+
+    /// ITRS to local east, north, up
+    pub fn enu_mat(&self) -> na::Matrix3<f64> {
+        let (sl, cl) = self.lon.sin_cos();
+        let (sp, cp) = self.lat.sin_cos();
+        na::Matrix3::new(-sl, cl, 0.0, -sp * cl, -sp * sl, cp, cp * cl, cp * sl, sp)
+    }
+
+    /// Altitude and azimuth (radians, azimuth from north through east) of a GCRS position in km
+    pub fn alt_az(&self, p: &GCRS, gcrs_to_itrs: &na::Matrix3<f64>) -> (f64, f64) {
+        let enu = self.enu_mat() * (gcrs_to_itrs * p.0 - self.itrs().0);
+        let alt = (enu.z / enu.norm()).asin();
+        let az = enu.x.atan2(enu.y).rem_euclid(std::f64::consts::TAU);
+        (alt, az)
+    }
+}
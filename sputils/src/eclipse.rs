@@ -0,0 +1,427 @@
+// Shadow geometry, Montenbruck & Gill "Satellite Orbits" section 3.4
+// Eclipse contacts follow the Explanatory Supplement (chapter 8), without Besselian elements
+// Epochs outside the ephemeris panic
+use crate::bodies;
+use crate::eph::{Body, JPL};
+use crate::search;
+use crate::time::TDB;
+use std::f64::consts::PI;
+
+/// Solar radius (km), IAU 2015 nominal
+pub const R_SUN: f64 = bodies::SUN.r_equatorial;
+/// Earth equatorial radius (km)
+pub const R_EARTH: f64 = bodies::EARTH.r_equatorial;
+/// Earth polar radius (km)
+pub const R_EARTH_POLAR: f64 = bodies::EARTH.r_polar;
+/// Mean lunar radius (km)
+pub const R_MOON: f64 = 1_737.4;
+/// Enlargement of Earth's shadow by the atmosphere (Danjon)
+pub const SHADOW_ENLARGEMENT: f64 = 1.0 + 1.0 / 85.0;
+
+// Contacts are refined to about a second
+const TOL_DAYS: f64 = 1.0 / 86400.0;
+
+/// Fraction of the Sun hidden at `r`, treating the shadow as a cylinder.
+///
+/// All positions share a frame and unit, `radius` is the occulting body radius.
+/// Returns 0 (lit) or 1 (shadow).
+pub fn shadow_cylindrical(
+    r: &na::Vector3<f64>,
+    sun: &na::Vector3<f64>,
+    body: &na::Vector3<f64>,
+    radius: f64,
+) -> f64 {
+    let s = (sun - body).normalize();
+    let p = r - body;
+    let along = p.dot(&s);
+    if along < 0.0 && (p - along * s).norm() < radius {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Fraction of the solar disk hidden at `r` by a spherical body (0 lit, 1 umbra).
+///
+/// Handles penumbra and annular (antumbra) geometry.
+pub fn shadow_conical(
+    r: &na::Vector3<f64>,
+    sun: &na::Vector3<f64>,
+    body: &na::Vector3<f64>,
+    radius: f64,
+) -> f64 {
+    let to_sun = sun - r;
+    let to_body = body - r;
+    // Apparent radii of sun and body, and their apparent separation
+    let a = (R_SUN / to_sun.norm()).min(1.0).asin();
+    let b = (radius / to_body.norm()).min(1.0).asin();
+    let c = to_sun.angle(&to_body);
+    if c >= a + b {
+        0.0
+    } else if c <= b - a {
+        1.0
+    } else if c <= a - b {
+        (b * b) / (a * a)
+    } else {
+        let x = (c * c + a * a - b * b) / (2.0 * c);
+        let y = (a * a - x * x).max(0.0).sqrt();
+        let area = a * a * (x / a).acos() + b * b * ((c - x) / b).acos() - c * y;
+        (area / (PI * a * a)).clamp(0.0, 1.0)
+    }
+}
+
+/// Conical shadow fraction for an oblate body with its symmetry axis along `pole`.
+///
+/// The polar axis is stretched so the body becomes a sphere of radius `r_eq`.
+pub fn shadow_oblate(
+    r: &na::Vector3<f64>,
+    sun: &na::Vector3<f64>,
+    body: &na::Vector3<f64>,
+    r_eq: f64,
+    r_polar: f64,
+    pole: &na::Vector3<f64>,
+) -> f64 {
+    let k = pole.normalize();
+    let stretch = |v: na::Vector3<f64>| v + (r_eq / r_polar - 1.0) * v.dot(&k) * k;
+    shadow_conical(
+        &(body + stretch(r - body)),
+        &(body + stretch(sun - body)),
+        body,
+        r_eq,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LunarEclipseKind {
+    Penumbral,
+    Partial,
+    Total,
+}
+
+/// Contact times of a lunar eclipse
+#[derive(Debug, Clone, Copy)]
+pub struct LunarEclipse {
+    pub kind: LunarEclipseKind,
+    /// Penumbra first touched
+    pub p1: TDB,
+    /// Umbra first touched
+    pub u1: Option<TDB>,
+    /// Totality begins
+    pub u2: Option<TDB>,
+    pub max: TDB,
+    /// Totality ends
+    pub u3: Option<TDB>,
+    /// Umbra last touched
+    pub u4: Option<TDB>,
+    /// Penumbra last touched
+    pub p4: TDB,
+    /// Fraction of the lunar diameter inside the umbra at maximum (negative if none)
+    pub umbral_magnitude: f64,
+    pub penumbral_magnitude: f64,
+}
+
+// Moon distance from the shadow axis, penumbra and umbra radii at the Moon (km)
+fn lunar_shadow(jpl: &mut JPL, t: TDB) -> (f64, f64, f64) {
+    let s = jpl
+        .position(t, Body::Sun, Body::Earth)
+        .expect("Epoch outside the ephemeris");
+    let m = jpl
+        .position(t, Body::Moon, Body::Earth)
+        .expect("Epoch outside the ephemeris");
+    let axis = -s.normalize();
+    let d = m.dot(&axis);
+    let rho = (m - d * axis).norm();
+    let ds = s.norm();
+    let r_p = (R_EARTH + d * (R_SUN + R_EARTH) / ds) * SHADOW_ENLARGEMENT;
+    let r_u = (R_EARTH - d * (R_SUN - R_EARTH) / ds) * SHADOW_ENLARGEMENT;
+    (rho, r_p, r_u)
+}
+
+/// Every lunar eclipse with maximum between `start` and `end`
+pub fn lunar_eclipses(jpl: &mut JPL, start: TDB, end: TDB) -> Vec<LunarEclipse> {
+    let at = |x: f64| TDB(start.0, start.1 + x);
+    let span = (end.0 - start.0) + (end.1 - start.1);
+    // Elongation from the antisolar point has one minimum per lunation
+    let closest = search::minima(
+        |x| {
+            let t = at(x);
+            let s = jpl
+                .position(t, Body::Sun, Body::Earth)
+                .expect("Epoch outside the ephemeris");
+            jpl.position(t, Body::Moon, Body::Earth)
+                .expect("Epoch outside the ephemeris")
+                .angle(&-s)
+        },
+        0.0,
+        span,
+        1.0,
+        TOL_DAYS,
+    );
+
+    let mut v = Vec::new();
+    for x in closest {
+        let (rho, r_p, r_u) = lunar_shadow(jpl, at(x));
+        let penumbral_magnitude = (r_p + R_MOON - rho) / (2.0 * R_MOON);
+        if penumbral_magnitude <= 0.0 {
+            continue;
+        }
+        let umbral_magnitude = (r_u + R_MOON - rho) / (2.0 * R_MOON);
+
+        // Contacts are all within half a day of maximum
+        let mut edge = |f: &dyn Fn(f64, f64, f64) -> f64, before: bool| {
+            let (x0, x1) = if before { (x - 0.5, x) } else { (x, x + 0.5) };
+            at(search::bisect(
+                |x| {
+                    let (rho, r_p, r_u) = lunar_shadow(jpl, at(x));
+                    f(rho, r_p, r_u)
+                },
+                x0,
+                x1,
+                TOL_DAYS,
+            ))
+        };
+        let pen = |rho: f64, r_p: f64, _: f64| rho - r_p - R_MOON;
+        let umb = |rho: f64, _: f64, r_u: f64| rho - r_u - R_MOON;
+        let tot = |rho: f64, _: f64, r_u: f64| rho - r_u + R_MOON;
+
+        let partial = umbral_magnitude > 0.0;
+        let total = umbral_magnitude >= 1.0;
+        v.push(LunarEclipse {
+            kind: if total {
+                LunarEclipseKind::Total
+            } else if partial {
+                LunarEclipseKind::Partial
+            } else {
+                LunarEclipseKind::Penumbral
+            },
+            p1: edge(&pen, true),
+            u1: if partial {
+                Some(edge(&umb, true))
+            } else {
+                None
+            },
+            u2: if total { Some(edge(&tot, true)) } else { None },
+            max: at(x),
+            u3: if total { Some(edge(&tot, false)) } else { None },
+            u4: if partial {
+                Some(edge(&umb, false))
+            } else {
+                None
+            },
+            p4: edge(&pen, false),
+            umbral_magnitude,
+            penumbral_magnitude,
+        });
+    }
+    v
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolarEclipseKind {
+    Partial,
+    Annular,
+    Total,
+}
+
+/// Global contacts of a solar eclipse (anywhere on Earth)
+#[derive(Debug, Clone, Copy)]
+pub struct SolarEclipse {
+    pub kind: SolarEclipseKind,
+    /// Penumbra first touches Earth
+    pub p1: TDB,
+    /// Shadow axis first touches Earth, for central eclipses
+    pub c1: Option<TDB>,
+    /// Shadow axis closest to Earth's centre
+    pub max: TDB,
+    /// Shadow axis last touches Earth
+    pub c2: Option<TDB>,
+    /// Penumbra last touches Earth
+    pub p4: TDB,
+    /// Least distance of the shadow axis from Earth's centre, in Earth radii
+    pub gamma: f64,
+}
+
+// Earth's distance from the lunar shadow axis, penumbra and umbra radii at Earth (km).
+// The polar axis is stretched (assuming Earth's pole along ICRF Z) so Earth can be treated as a sphere.
+// A negative umbra radius is the antumbra.
+fn solar_shadow(jpl: &mut JPL, t: TDB) -> (f64, f64, f64) {
+    let k = na::Vector3::z();
+    let stretch = |v: na::Vector3<f64>| v + (R_EARTH / R_EARTH_POLAR - 1.0) * v.dot(&k) * k;
+    let s = stretch(
+        jpl.position(t, Body::Sun, Body::Earth)
+            .expect("Epoch outside the ephemeris"),
+    );
+    let m = stretch(
+        jpl.position(t, Body::Moon, Body::Earth)
+            .expect("Epoch outside the ephemeris"),
+    );
+    let axis = (m - s).normalize();
+    let e = -m;
+    let d = e.dot(&axis);
+    let rho = (e - d * axis).norm();
+    let ds = (m - s).norm();
+    let r_p = R_MOON + d * (R_SUN + R_MOON) / ds;
+    let r_u = R_MOON - d * (R_SUN - R_MOON) / ds;
+    (rho, r_p, r_u)
+}
+
+/// Every solar eclipse with greatest eclipse between `start` and `end`
+pub fn solar_eclipses(jpl: &mut JPL, start: TDB, end: TDB) -> Vec<SolarEclipse> {
+    let at = |x: f64| TDB(start.0, start.1 + x);
+    let span = (end.0 - start.0) + (end.1 - start.1);
+    // Elongation from the Sun has one minimum per lunation
+    let closest = search::minima(
+        |x| {
+            let t = at(x);
+            let s = jpl
+                .position(t, Body::Sun, Body::Earth)
+                .expect("Epoch outside the ephemeris");
+            jpl.position(t, Body::Moon, Body::Earth)
+                .expect("Epoch outside the ephemeris")
+                .angle(&s)
+        },
+        0.0,
+        span,
+        1.0,
+        TOL_DAYS,
+    );
+
+    let mut v = Vec::new();
+    for x in closest {
+        // The elongation minimum is close to, but not exactly, greatest eclipse
+        let x = search::minimize(|x| solar_shadow(jpl, at(x)).0, x - 0.25, x + 0.25, TOL_DAYS);
+        let (rho, r_p, r_u) = solar_shadow(jpl, at(x));
+        if rho >= r_p + R_EARTH {
+            continue;
+        }
+
+        let mut edge = |f: &dyn Fn(f64, f64, f64) -> f64, before: bool| {
+            let (x0, x1) = if before { (x - 0.5, x) } else { (x, x + 0.5) };
+            at(search::bisect(
+                |x| {
+                    let (rho, r_p, r_u) = solar_shadow(jpl, at(x));
+                    f(rho, r_p, r_u)
+                },
+                x0,
+                x1,
+                TOL_DAYS,
+            ))
+        };
+        let pen = |rho: f64, r_p: f64, _: f64| rho - r_p - R_EARTH;
+        let axis = |rho: f64, _: f64, _: f64| rho - R_EARTH;
+
+        // Non-central eclipses can still be total/annular near the limb
+        let central = rho < R_EARTH;
+        let kind = if rho < R_EARTH + r_u.abs() {
+            if r_u > 0.0 {
+                SolarEclipseKind::Total
+            } else {
+                SolarEclipseKind::Annular
+            }
+        } else {
+            SolarEclipseKind::Partial
+        };
+        v.push(SolarEclipse {
+            kind,
+            p1: edge(&pen, true),
+            c1: if central {
+                Some(edge(&axis, true))
+            } else {
+                None
+            },
+            max: at(x),
+            c2: if central {
+                Some(edge(&axis, false))
+            } else {
+                None
+            },
+            p4: edge(&pen, false),
+            gamma: rho / R_EARTH,
+        });
+    }
+    v
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowEvent {
+    PenumbraEntry,
+    UmbraEntry,
+    UmbraExit,
+    PenumbraExit,
+}
+
+// Apparent separation of Sun and Earth minus the penumbra and umbra limits (radians)
+fn satellite_shadow(jpl: &mut JPL, t: TDB, r: &na::Vector3<f64>) -> (f64, f64) {
+    let sun = jpl
+        .position(t, Body::Sun, Body::Earth)
+        .expect("Epoch outside the ephemeris");
+    let to_sun = sun - r;
+    let a = (R_SUN / to_sun.norm()).asin();
+    let b = (R_EARTH / r.norm()).min(1.0).asin();
+    let c = to_sun.angle(&-r);
+    (c - (a + b), c - (b - a))
+}
+
+/// Shadow entry and exit of an Earth satellite between `start` and `end`.
+///
+/// `pos` gives the geocentric position (km, ICRF axes) at an epoch.
+/// `step` (days) must be shorter than the briefest penumbra crossing, a minute works for LEO.
+pub fn satellite_eclipses<F>(
+    jpl: &mut JPL,
+    mut pos: F,
+    start: TDB,
+    end: TDB,
+    step: f64,
+) -> Vec<(TDB, ShadowEvent)>
+where
+    F: FnMut(TDB) -> na::Vector3<f64>,
+{
+    let at = |x: f64| TDB(start.0, start.1 + x);
+    let span = (end.0 - start.0) + (end.1 - start.1);
+    let mut v: Vec<(f64, ShadowEvent)> = Vec::new();
+
+    let pen = search::roots(
+        |x| {
+            let t = at(x);
+            satellite_shadow(jpl, t, &pos(t)).0
+        },
+        0.0,
+        span,
+        step,
+        TOL_DAYS,
+    );
+    v.extend(pen.into_iter().map(|(x, rising)| {
+        (
+            x,
+            if rising {
+                ShadowEvent::PenumbraExit
+            } else {
+                ShadowEvent::PenumbraEntry
+            },
+        )
+    }));
+
+    let umb = search::roots(
+        |x| {
+            let t = at(x);
+            satellite_shadow(jpl, t, &pos(t)).1
+        },
+        0.0,
+        span,
+        step,
+        TOL_DAYS,
+    );
+    v.extend(umb.into_iter().map(|(x, rising)| {
+        (
+            x,
+            if rising {
+                ShadowEvent::UmbraExit
+            } else {
+                ShadowEvent::UmbraEntry
+            },
+        )
+    }));
+
+    v.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    v.into_iter().map(|(x, e)| (at(x), e)).collect()
+}
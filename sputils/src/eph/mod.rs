@@ -2,6 +2,51 @@ use jpl_sys::*;
 
 // TODO provide enums for all things
 
+/// Target/center numbering used by `jpl_pleph`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Body {
+    Mercury = 1,
+    Venus = 2,
+    Earth = 3,
+    Mars = 4,
+    Jupiter = 5,
+    Saturn = 6,
+    Uranus = 7,
+    Neptune = 8,
+    Pluto = 9,
+    Moon = 10,
+    Sun = 11,
+    SSB = 12,
+    EMB = 13,
+}
+
+/// Major natural satellites, not covered by the planetary ephemeris
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Satellite {
+    Phobos,
+    Deimos,
+    Io,
+    Europa,
+    Ganymede,
+    Callisto,
+    Titan,
+    Triton,
+    Charon,
+}
+
+impl Satellite {
+    /// Planet the satellite orbits
+    pub fn primary(self) -> Body {
+        match self {
+            Self::Phobos | Self::Deimos => Body::Mars,
+            Self::Io | Self::Europa | Self::Ganymede | Self::Callisto => Body::Jupiter,
+            Self::Titan => Body::Saturn,
+            Self::Triton => Body::Neptune,
+            Self::Charon => Body::Pluto,
+        }
+    }
+}
+
 #[repr(transparent)]
 pub struct JPL(*mut std::os::raw::c_void);
 
@@ -21,28 +66,79 @@ impl JPL {
         }
     }
 
-    fn pleph(&mut self, t: crate::time::TDB, ntarg: i32, ncent: i32) -> [f64; 3] {
+    // Errors with jpl_pleph's nonzero return code, such as an epoch outside the file
+    fn pleph(&mut self, t: crate::time::TDB, ntarg: i32, ncent: i32) -> Result<[f64; 3], i32> {
         unsafe {
             let mut data = [0.0_f64; 6];
-            jpl_pleph(self.0, t.0 + t.1, ntarg, ncent, data.as_mut_ptr(), 0);
+            let err = jpl_pleph(self.0, t.0 + t.1, ntarg, ncent, data.as_mut_ptr(), 0);
+            if err != 0 {
+                return Err(err);
+            }
             let mut pos = [0.0_f64; 3];
             pos.copy_from_slice(&data[..3]);
-            pos
+            Ok(pos)
         }
     }
 
-    fn pleph_vel(&mut self, t: crate::time::TDB, ntarg: i32, ncent: i32) -> ([f64; 3], [f64; 3]) {
+    fn pleph_vel(
+        &mut self,
+        t: crate::time::TDB,
+        ntarg: i32,
+        ncent: i32,
+    ) -> Result<([f64; 3], [f64; 3]), i32> {
         unsafe {
             let mut data = [0.0_f64; 6];
-            jpl_pleph(self.0, t.0 + t.1, ntarg, ncent, data.as_mut_ptr(), 1);
-            std::mem::transmute(data)
+            let err = jpl_pleph(self.0, t.0 + t.1, ntarg, ncent, data.as_mut_ptr(), 1);
+            if err != 0 {
+                return Err(err);
+            }
+            Ok(std::mem::transmute(data))
         }
     }
 
+    /// Length of an AU in km, as stored in the ephemeris header
+    pub fn au(&self) -> f64 {
+        unsafe { jpl_get_double(self.0, JPL_EPHEM_AU_IN_KM as _) }
+    }
+
+    /// Position (km) of `target` relative to `center`, ICRF axes.
+    ///
+    /// Errors with `jpl_pleph`'s code, such as when `t` is outside the file
+    pub fn position(
+        &mut self,
+        t: crate::time::TDB,
+        target: Body,
+        center: Body,
+    ) -> Result<na::Vector3<f64>, i32> {
+        let p = self.pleph(t, target as i32, center as i32)?;
+        Ok(na::Vector3::from_column_slice(&p) * self.au())
+    }
+
+    /// Position (km) and velocity (km/s) of `target` relative to `center`, ICRF axes.
+    ///
+    /// Errors like `position`
+    pub fn state(
+        &mut self,
+        t: crate::time::TDB,
+        target: Body,
+        center: Body,
+    ) -> Result<(na::Vector3<f64>, na::Vector3<f64>), i32> {
+        let au = self.au();
+        let (p, v) = self.pleph_vel(t, target as i32, center as i32)?;
+        Ok((
+            na::Vector3::from_column_slice(&p) * au,
+            na::Vector3::from_column_slice(&v) * (au / sofa_sys::DAYSEC),
+        ))
+    }
+
+    // Zeros when `t` is outside the file
     pub fn moon(&mut self, t: crate::time::TDB) -> (crate::coord::GCRS, na::Vector3<f64>) {
-        let pos = crate::coord::GCRS(na::Vector3::from_column_slice(&self.pleph(t, 10, 3)));
-        let lib = na::Vector3::from_column_slice(&self.pleph(t, 15, 0));
-        (pos, lib)
+        let pos = self.pleph(t, 10, 3).unwrap_or_default();
+        let lib = self.pleph(t, 15, 0).unwrap_or_default();
+        (
+            crate::coord::GCRS(na::Vector3::from_column_slice(&pos)),
+            na::Vector3::from_column_slice(&lib),
+        )
     }
 }
 
@@ -0,0 +1,374 @@
+// Izzo, D. (2015) "Revisiting Lambert's problem"
+// https://arxiv.org/abs/1403.2705
+// Structure follows the poliastro/pykep implementations
+use std::f64::consts::PI;
+
+const MAX_ITER: u32 = 35;
+const TOLERANCE: f64 = 1e-8;
+
+#[derive(Debug, Clone, Copy)]
+pub enum LambertError {
+    /// Input vectors are degenerate (zero length, collinear, or non-positive time of flight)
+    InvalidInput,
+    /// No solution with the requested number of revolutions
+    NoSolution,
+    /// Root finding ran out of iterations
+    NotConverged,
+}
+
+impl std::error::Error for LambertError {}
+impl std::fmt::Display for LambertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::InvalidInput => write!(f, "Invalid Lambert problem"),
+            Self::NoSolution => write!(f, "No Lambert solution"),
+            Self::NotConverged => write!(f, "Lambert solver did not converge"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LambertSolution {
+    /// Velocity at `r1`
+    pub v1: na::Vector3<f64>,
+    /// Velocity at `r2`
+    pub v2: na::Vector3<f64>,
+    /// Complete revolutions
+    pub revs: u32,
+    /// For multi-revolution solutions, whether this is the low (left) branch
+    pub low_path: bool,
+}
+
+/// Every solution with up to `max_revs` complete revolutions.
+///
+/// Positions and `mu` must share a length unit, `tof` is in seconds.
+/// The zero revolution solution comes first, followed by a low/high path pair per revolution.
+pub fn lambert(
+    r1: &na::Vector3<f64>,
+    r2: &na::Vector3<f64>,
+    tof: f64,
+    mu: f64,
+    prograde: bool,
+    max_revs: u32,
+) -> Result<Vec<LambertSolution>, LambertError> {
+    let geo = Geometry::new(r1, r2, tof, mu, prograde)?;
+    let m_max = max_revs.min(geo.max_revs());
+
+    let mut v = Vec::with_capacity(1 + 2 * m_max as usize);
+    v.push(geo.solve(0, true)?);
+    for m in 1..=m_max {
+        // A branch may still fail close to the minimum time of flight
+        for &low_path in &[true, false] {
+            if let Ok(s) = geo.solve(m, low_path) {
+                v.push(s);
+            }
+        }
+    }
+    Ok(v)
+}
+
+/// Single solution with exactly `revs` complete revolutions
+pub fn lambert_single(
+    r1: &na::Vector3<f64>,
+    r2: &na::Vector3<f64>,
+    tof: f64,
+    mu: f64,
+    prograde: bool,
+    revs: u32,
+    low_path: bool,
+) -> Result<LambertSolution, LambertError> {
+    let geo = Geometry::new(r1, r2, tof, mu, prograde)?;
+    if revs > geo.max_revs() {
+        return Err(LambertError::NoSolution);
+    }
+    geo.solve(revs, low_path)
+}
+
+struct Geometry {
+    r1: f64,
+    r2: f64,
+    c: f64,
+    ir1: na::Vector3<f64>,
+    ir2: na::Vector3<f64>,
+    it1: na::Vector3<f64>,
+    it2: na::Vector3<f64>,
+    ll: f64,
+    // Non-dimensional time of flight
+    t: f64,
+    gamma: f64,
+}
+
+impl Geometry {
+    fn new(
+        r1v: &na::Vector3<f64>,
+        r2v: &na::Vector3<f64>,
+        tof: f64,
+        mu: f64,
+        prograde: bool,
+    ) -> Result<Self, LambertError> {
+        let r1 = r1v.norm();
+        let r2 = r2v.norm();
+        let c = (r2v - r1v).norm();
+        if !(tof > 0.0 && mu > 0.0 && r1 > 0.0 && r2 > 0.0 && c > 0.0) {
+            return Err(LambertError::InvalidInput);
+        }
+        let s = (r1 + r2 + c) * 0.5;
+        let ir1 = r1v / r1;
+        let ir2 = r2v / r2;
+        let ih = ir1.cross(&ir2);
+        let ih_norm = ih.norm();
+        // 180 degree transfers have no defined plane
+        if ih_norm < 1e-12 {
+            return Err(LambertError::InvalidInput);
+        }
+        let ih = ih / ih_norm;
+
+        let mut ll = (1.0 - (c / s).min(1.0)).sqrt();
+        let (mut it1, mut it2) = if ih.z < 0.0 {
+            ll = -ll;
+            (ir1.cross(&ih), ir2.cross(&ih))
+        } else {
+            (ih.cross(&ir1), ih.cross(&ir2))
+        };
+        if !prograde {
+            ll = -ll;
+            it1 = -it1;
+            it2 = -it2;
+        }
+
+        Ok(Self {
+            r1,
+            r2,
+            c,
+            ir1,
+            ir2,
+            it1,
+            it2,
+            ll,
+            t: (2.0 * mu / (s * s * s)).sqrt() * tof,
+            gamma: (mu * s / 2.0).sqrt(),
+        })
+    }
+
+    fn max_revs(&self) -> u32 {
+        let mut m_max = (self.t / PI).floor();
+        let t00 = self.ll.acos() + self.ll * (1.0 - self.ll * self.ll).sqrt();
+        if self.t < t00 + m_max * PI && m_max > 0.0 {
+            if let Ok(t_min) = t_min(self.ll, m_max as u32) {
+                if self.t < t_min {
+                    m_max -= 1.0;
+                }
+            }
+        }
+        m_max.max(0.0) as u32
+    }
+
+    fn solve(&self, m: u32, low_path: bool) -> Result<LambertSolution, LambertError> {
+        let ll = self.ll;
+        let x = householder(initial_guess(self.t, ll, m, low_path), self.t, ll, m)?;
+        let y = compute_y(x, ll);
+
+        let rho = (self.r1 - self.r2) / self.c;
+        let sigma = (1.0 - rho * rho).sqrt();
+        let vr1 = self.gamma * ((ll * y - x) - rho * (ll * y + x)) / self.r1;
+        let vr2 = -self.gamma * ((ll * y - x) + rho * (ll * y + x)) / self.r2;
+        let vt1 = self.gamma * sigma * (y + ll * x) / self.r1;
+        let vt2 = self.gamma * sigma * (y + ll * x) / self.r2;
+
+        Ok(LambertSolution {
+            v1: vr1 * self.ir1 + vt1 * self.it1,
+            v2: vr2 * self.ir2 + vt2 * self.it2,
+            revs: m,
+            low_path,
+        })
+    }
+}
+
+fn compute_y(x: f64, ll: f64) -> f64 {
+    (1.0 - ll * ll * (1.0 - x * x)).sqrt()
+}
+
+fn compute_psi(x: f64, y: f64, ll: f64) -> f64 {
+    if (-1.0..1.0).contains(&x) {
+        (x * y + ll * (1.0 - x * x)).acos()
+    } else if x > 1.0 {
+        ((y - x * ll) * (x * x - 1.0).sqrt()).asinh()
+    } else {
+        0.0
+    }
+}
+
+// Gauss hypergeometric 2F1(3, 1, 5/2, x), only used close to x = 1
+fn hyp2f1b(x: f64) -> f64 {
+    if x >= 1.0 {
+        return f64::INFINITY;
+    }
+    let mut res = 1.0;
+    let mut term = 1.0;
+    let mut i = 0.0;
+    loop {
+        term *= (3.0 + i) * (1.0 + i) / (2.5 + i) * x / (i + 1.0);
+        let old = res;
+        res += term;
+        if old == res {
+            return res;
+        }
+        i += 1.0;
+    }
+}
+
+fn tof_equation_y(x: f64, y: f64, t0: f64, ll: f64, m: u32) -> f64 {
+    let t = if m == 0 && x > 0.6_f64.sqrt() && x < 1.4_f64.sqrt() {
+        // Battin series, avoids the singularity at x = 1
+        let eta = y - ll * x;
+        let s1 = (1.0 - ll - x * eta) * 0.5;
+        let q = 4.0 / 3.0 * hyp2f1b(s1);
+        (eta * eta * eta * q + 4.0 * ll * eta) * 0.5
+    } else {
+        let psi = compute_psi(x, y, ll);
+        ((psi + m as f64 * PI) / (1.0 - x * x).abs().sqrt() - x + ll * y) / (1.0 - x * x)
+    };
+    t - t0
+}
+
+fn tof_equation(x: f64, t0: f64, ll: f64, m: u32) -> f64 {
+    tof_equation_y(x, compute_y(x, ll), t0, ll, m)
+}
+
+fn tof_p(x: f64, y: f64, t: f64, ll: f64) -> f64 {
+    (3.0 * t * x - 2.0 + 2.0 * ll.powi(3) * x / y) / (1.0 - x * x)
+}
+
+fn tof_p2(x: f64, y: f64, t: f64, dt: f64, ll: f64) -> f64 {
+    (3.0 * t + 5.0 * x * dt + 2.0 * (1.0 - ll * ll) * ll.powi(3) / y.powi(3)) / (1.0 - x * x)
+}
+
+fn tof_p3(x: f64, y: f64, dt: f64, ddt: f64, ll: f64) -> f64 {
+    (7.0 * x * ddt + 8.0 * dt - 6.0 * (1.0 - ll * ll) * ll.powi(5) * x / y.powi(5)) / (1.0 - x * x)
+}
+
+// Minimum non-dimensional time of flight for `m` revolutions
+fn t_min(ll: f64, m: u32) -> Result<f64, LambertError> {
+    if ll == 1.0 {
+        Ok(tof_equation(0.0, 0.0, ll, m))
+    } else if m == 0 {
+        Ok(0.0)
+    } else {
+        let x = halley(0.1, tof_equation(0.1, 0.0, ll, m), ll)?;
+        Ok(tof_equation(x, 0.0, ll, m))
+    }
+}
+
+fn initial_guess(t: f64, ll: f64, m: u32, low_path: bool) -> f64 {
+    if m == 0 {
+        let t0 = ll.acos() + ll * (1.0 - ll * ll).sqrt();
+        let t1 = 2.0 * (1.0 - ll.powi(3)) / 3.0;
+        if t >= t0 {
+            (t0 / t).powf(2.0 / 3.0) - 1.0
+        } else if t < t1 {
+            2.5 * t1 / t * (t1 - t) / (1.0 - ll.powi(5)) + 1.0
+        } else {
+            (t0 / t).powf((t1 / t0).log2()) - 1.0
+        }
+    } else {
+        let m = m as f64;
+        let a = ((m * PI + PI) / (8.0 * t)).powf(2.0 / 3.0);
+        let b = ((8.0 * t) / (m * PI)).powf(2.0 / 3.0);
+        let x0l = (a - 1.0) / (a + 1.0);
+        let x0r = (b - 1.0) / (b + 1.0);
+        if low_path {
+            x0l.max(x0r)
+        } else {
+            x0l.min(x0r)
+        }
+    }
+}
+
+// Stationary point of the time of flight curve
+fn halley(mut p0: f64, t0: f64, ll: f64) -> Result<f64, LambertError> {
+    for _ in 0..MAX_ITER {
+        let y = compute_y(p0, ll);
+        let d = tof_p(p0, y, t0, ll);
+        let d2 = tof_p2(p0, y, t0, d, ll);
+        if d2 == 0.0 {
+            return Err(LambertError::NotConverged);
+        }
+        let d3 = tof_p3(p0, y, d, d2, ll);
+        let p = p0 - 2.0 * d * d2 / (2.0 * d2 * d2 - d * d3);
+        if (p - p0).abs() < TOLERANCE {
+            return Ok(p);
+        }
+        p0 = p;
+    }
+    Err(LambertError::NotConverged)
+}
+
+fn householder(mut p0: f64, t0: f64, ll: f64, m: u32) -> Result<f64, LambertError> {
+    for _ in 0..MAX_ITER {
+        let y = compute_y(p0, ll);
+        let f = tof_equation_y(p0, y, t0, ll, m);
+        let t = f + t0;
+        let d = tof_p(p0, y, t, ll);
+        let d2 = tof_p2(p0, y, t, d, ll);
+        let d3 = tof_p3(p0, y, d, d2, ll);
+        let p = p0 - f * ((d * d - f * d2 / 2.0) / (d * (d * d - f * d2) + d3 * f * f / 6.0));
+        if !p.is_finite() {
+            return Err(LambertError::NotConverged);
+        }
+        if (p - p0).abs() < TOLERANCE {
+            return Ok(p);
+        }
+        p0 = p;
+    }
+    Err(LambertError::NotConverged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MU_EARTH: f64 = 398600.4418;
+
+    // Vallado (2013) example 7-5
+    #[test]
+    fn vallado() {
+        let r1 = na::Vector3::new(15945.34, 0.0, 0.0);
+        let r2 = na::Vector3::new(12214.83399, 10249.46731, 0.0);
+        let s = lambert_single(&r1, &r2, 76.0 * 60.0, MU_EARTH, true, 0, true).unwrap();
+        assert!((s.v1 - na::Vector3::new(2.058925, 2.915956, 0.0)).norm() < 1e-4);
+        assert!((s.v2 - na::Vector3::new(-3.451569, 0.910301, 0.0)).norm() < 1e-4);
+    }
+
+    // Every solution is a single conic, so energy and angular momentum match at both ends
+    #[test]
+    fn multi_revolution() {
+        let r1 = na::Vector3::new(7000.0, 0.0, 0.0);
+        let r2 = na::Vector3::new(0.0, 8000.0, 0.0);
+        let v = lambert(&r1, &r2, 20000.0, MU_EARTH, true, 5).unwrap();
+        assert!(v.len() >= 3);
+        assert_eq!(v[0].revs, 0);
+        for s in &v {
+            let e1 = s.v1.norm_squared() / 2.0 - MU_EARTH / r1.norm();
+            let e2 = s.v2.norm_squared() / 2.0 - MU_EARTH / r2.norm();
+            assert!((e1 - e2).abs() < 1e-6 * e1.abs());
+            let (h1, h2) = (r1.cross(&s.v1), r2.cross(&s.v2));
+            assert!((h1 - h2).norm() < 1e-6 * h1.norm());
+            // Prograde about +Z
+            assert!(h1.z > 0.0);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let r1 = na::Vector3::new(7000.0, 0.0, 0.0);
+        let r2 = na::Vector3::new(0.0, 8000.0, 0.0);
+        assert!(matches!(
+            lambert(&r1, &r2, 0.0, MU_EARTH, true, 0),
+            Err(LambertError::InvalidInput)
+        ));
+        assert!(matches!(
+            lambert(&na::Vector3::zeros(), &r2, 1000.0, MU_EARTH, true, 0),
+            Err(LambertError::InvalidInput)
+        ));
+    }
+}
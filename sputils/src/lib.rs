@@ -1,8 +1,16 @@
 extern crate nalgebra as na;
 use sofa_sys::*;
 
+pub mod almanac;
+pub mod analytic;
+pub mod bodies;
 pub mod coord;
+pub mod eclipse;
 pub mod eph;
+pub mod lambert;
+pub mod orient;
+pub mod porkchop;
+pub mod search;
 pub mod time;
 
 #[inline(always)]
@@ -0,0 +1,277 @@
+// IAU WGCCRE rotation models
+// Archinal et al. (2011, 2018) "Report of the IAU Working Group on Cartographic Coordinates and Rotational Elements"
+// Angles in the tables are degrees, `d` is days and `t` Julian centuries from J2000 TDB
+use crate::eph::{Body, Satellite};
+use crate::time::TDB;
+use sofa_sys::{DD2R, DJ00, DJC};
+
+/// Pole right ascension, pole declination and prime meridian angle (radians, ICRF)
+#[derive(Debug, Clone, Copy)]
+pub struct PoleAngles {
+    pub ra: f64,
+    pub dec: f64,
+    pub w: f64,
+}
+
+impl PoleAngles {
+    fn from_deg(ra: f64, dec: f64, w: f64) -> Self {
+        Self {
+            ra: ra * DD2R,
+            dec: dec * DD2R,
+            w: (w * DD2R).rem_euclid(std::f64::consts::TAU),
+        }
+    }
+
+    /// Body-fixed to ICRF
+    pub fn matrix(&self) -> na::Matrix3<f64> {
+        euler_313(
+            self.ra + std::f64::consts::FRAC_PI_2,
+            std::f64::consts::FRAC_PI_2 - self.dec,
+            self.w,
+        )
+    }
+
+    /// North pole direction in ICRF
+    pub fn pole(&self) -> na::Vector3<f64> {
+        na::Vector3::new(
+            self.dec.cos() * self.ra.cos(),
+            self.dec.cos() * self.ra.sin(),
+            self.dec.sin(),
+        )
+    }
+}
+
+// Rz(a) Rx(b) Rz(c), active rotations
+fn euler_313(a: f64, b: f64, c: f64) -> na::Matrix3<f64> {
+    (na::Rotation3::from_axis_angle(&na::Vector3::z_axis(), a)
+        * na::Rotation3::from_axis_angle(&na::Vector3::x_axis(), b)
+        * na::Rotation3::from_axis_angle(&na::Vector3::z_axis(), c))
+    .into_inner()
+}
+
+/// Rotation matrix as a homogeneous transform (no translation), the layout `stardome::Planet::tf` uses
+pub fn homogeneous(m: &na::Matrix3<f64>) -> na::Matrix4<f64> {
+    m.to_homogeneous()
+}
+
+fn days(t: TDB) -> f64 {
+    (t.0 - DJ00) + t.1
+}
+
+#[inline(always)]
+fn sin(deg: f64) -> f64 {
+    (deg * DD2R).sin()
+}
+
+#[inline(always)]
+fn cos(deg: f64) -> f64 {
+    (deg * DD2R).cos()
+}
+
+/// IAU rotation model of a body, `None` for barycentres
+pub fn body(b: Body, t: TDB) -> Option<PoleAngles> {
+    let d = days(t);
+    let t = d / DJC;
+    Some(match b {
+        Body::Sun => PoleAngles::from_deg(286.13, 63.87, 84.176 + 14.1844000 * d),
+        Body::Mercury => {
+            let m1 = 174.7910857 + 4.092335 * d;
+            let m2 = 349.5821714 + 8.184670 * d;
+            let m3 = 164.3732571 + 12.277005 * d;
+            let m4 = 339.1643429 + 16.369340 * d;
+            let m5 = 153.9554286 + 20.461675 * d;
+            PoleAngles::from_deg(
+                281.0103 - 0.0328 * t,
+                61.4155 - 0.0049 * t,
+                329.5988 + 6.1385108 * d + 0.01067257 * sin(m1)
+                    - 0.00112309 * sin(m2)
+                    - 0.00011040 * sin(m3)
+                    - 0.00002539 * sin(m4)
+                    - 0.00000571 * sin(m5),
+            )
+        }
+        Body::Venus => PoleAngles::from_deg(272.76, 67.16, 160.20 - 1.4813688 * d),
+        // Low precision, use `crate::coord::GCRS::gcrs_to_itrs_mat` for the real thing
+        Body::Earth => PoleAngles::from_deg(
+            0.00 - 0.641 * t,
+            90.00 - 0.557 * t,
+            190.147 + 360.9856235 * d,
+        ),
+        Body::Mars => PoleAngles::from_deg(
+            317.68143 - 0.1061 * t,
+            52.88650 - 0.0609 * t,
+            176.630 + 350.89198226 * d,
+        ),
+        Body::Jupiter => {
+            let ja = 99.360714 + 4850.4046 * t;
+            let jb = 175.895369 + 1191.9605 * t;
+            let jc = 300.323162 + 262.5475 * t;
+            let jd = 114.012305 + 6070.2476 * t;
+            let je = 49.511251 + 64.3000 * t;
+            PoleAngles::from_deg(
+                268.056595 - 0.006499 * t
+                    + 0.000117 * sin(ja)
+                    + 0.000938 * sin(jb)
+                    + 0.001432 * sin(jc)
+                    + 0.000030 * sin(jd)
+                    + 0.002150 * sin(je),
+                64.495303
+                    + 0.002413 * t
+                    + 0.000050 * cos(ja)
+                    + 0.000404 * cos(jb)
+                    + 0.000617 * cos(jc)
+                    - 0.000013 * cos(jd)
+                    + 0.000926 * cos(je),
+                284.95 + 870.5360000 * d,
+            )
+        }
+        Body::Saturn => PoleAngles::from_deg(
+            40.589 - 0.036 * t,
+            83.537 - 0.004 * t,
+            38.90 + 810.7939024 * d,
+        ),
+        Body::Uranus => PoleAngles::from_deg(257.311, -15.175, 203.81 - 501.1600928 * d),
+        Body::Neptune => {
+            let n = 357.85 + 52.316 * t;
+            PoleAngles::from_deg(
+                299.36 + 0.70 * sin(n),
+                43.46 - 0.51 * cos(n),
+                249.978 + 541.1397757 * d - 0.48 * sin(n),
+            )
+        }
+        Body::Pluto => PoleAngles::from_deg(132.993, -6.163, 302.695 + 56.3625225 * d),
+        // Mean Earth/polar axis frame
+        Body::Moon => {
+            let e1 = 125.045 - 0.0529921 * d;
+            let e2 = 250.089 - 0.1059842 * d;
+            let e3 = 260.008 + 13.0120009 * d;
+            let e4 = 176.625 + 13.3407154 * d;
+            let e5 = 357.529 + 0.9856003 * d;
+            let e6 = 311.589 + 26.4057084 * d;
+            let e7 = 134.963 + 13.0649930 * d;
+            let e8 = 276.617 + 0.3287146 * d;
+            let e9 = 34.226 + 1.7484877 * d;
+            let e10 = 15.134 - 0.1589763 * d;
+            let e11 = 119.743 + 0.0036096 * d;
+            let e12 = 239.961 + 0.1643573 * d;
+            let e13 = 25.053 + 12.9590088 * d;
+            PoleAngles::from_deg(
+                269.9949 + 0.0031 * t - 3.8787 * sin(e1) - 0.1204 * sin(e2) + 0.0700 * sin(e3)
+                    - 0.0172 * sin(e4)
+                    + 0.0072 * sin(e6)
+                    - 0.0052 * sin(e10)
+                    + 0.0043 * sin(e13),
+                66.5392 + 0.0130 * t + 1.5419 * cos(e1) + 0.0239 * cos(e2) - 0.0278 * cos(e3)
+                    + 0.0068 * cos(e4)
+                    - 0.0029 * cos(e6)
+                    + 0.0009 * cos(e7)
+                    + 0.0008 * cos(e10)
+                    - 0.0009 * cos(e13),
+                38.3213 + 13.17635815 * d - 1.4e-12 * d * d + 3.5610 * sin(e1) + 0.1208 * sin(e2)
+                    - 0.0642 * sin(e3)
+                    + 0.0158 * sin(e4)
+                    + 0.0252 * sin(e5)
+                    - 0.0066 * sin(e6)
+                    - 0.0047 * sin(e7)
+                    - 0.0046 * sin(e8)
+                    + 0.0028 * sin(e9)
+                    + 0.0052 * sin(e10)
+                    + 0.0040 * sin(e11)
+                    + 0.0019 * sin(e12)
+                    - 0.0044 * sin(e13),
+            )
+        }
+        Body::SSB | Body::EMB => return None,
+    })
+}
+
+/// IAU rotation model of a satellite
+pub fn satellite(s: Satellite, t: TDB) -> PoleAngles {
+    let d = days(t);
+    let t = d / DJC;
+    // Jupiter system arguments
+    let j3 = 283.90 + 4850.7 * t;
+    let j4 = 355.80 + 1191.3 * t;
+    let j5 = 119.90 + 262.1 * t;
+    let j6 = 229.80 + 64.3 * t;
+    let j7 = 352.25 + 2382.6 * t;
+    let j8 = 113.35 + 6070.0 * t;
+    match s {
+        Satellite::Phobos => {
+            let m1 = 169.51 - 0.4357640 * d;
+            let m2 = 192.93 + 1128.4096700 * d + 8.864 * t * t;
+            PoleAngles::from_deg(
+                317.68 - 0.108 * t + 1.79 * sin(m1),
+                52.90 - 0.061 * t - 1.08 * cos(m1),
+                35.06 + 1128.8445850 * d + 8.864 * t * t - 1.42 * sin(m1) - 0.78 * sin(m2),
+            )
+        }
+        Satellite::Deimos => {
+            let m3 = 53.47 - 0.0181510 * d;
+            PoleAngles::from_deg(
+                316.65 - 0.108 * t + 2.98 * sin(m3),
+                53.52 - 0.061 * t - 1.78 * cos(m3),
+                79.41 + 285.1618970 * d - 0.520 * t * t - 2.58 * sin(m3) + 0.19 * cos(m3),
+            )
+        }
+        Satellite::Io => PoleAngles::from_deg(
+            268.05 - 0.009 * t + 0.094 * sin(j3) + 0.024 * sin(j4),
+            64.50 + 0.003 * t + 0.040 * cos(j3) + 0.011 * cos(j4),
+            200.39 + 203.4889538 * d - 0.085 * sin(j3) - 0.022 * sin(j4),
+        ),
+        Satellite::Europa => PoleAngles::from_deg(
+            268.08 - 0.009 * t
+                + 1.086 * sin(j4)
+                + 0.060 * sin(j5)
+                + 0.015 * sin(j6)
+                + 0.009 * sin(j7),
+            64.51
+                + 0.003 * t
+                + 0.468 * cos(j4)
+                + 0.026 * cos(j5)
+                + 0.007 * cos(j6)
+                + 0.002 * cos(j7),
+            36.022 + 101.3747235 * d
+                - 0.980 * sin(j4)
+                - 0.054 * sin(j5)
+                - 0.014 * sin(j6)
+                - 0.008 * sin(j7),
+        ),
+        Satellite::Ganymede => PoleAngles::from_deg(
+            268.20 - 0.009 * t - 0.037 * sin(j4) + 0.431 * sin(j5) + 0.091 * sin(j6),
+            64.57 + 0.003 * t - 0.016 * cos(j4) + 0.186 * cos(j5) + 0.039 * cos(j6),
+            44.064 + 50.3176081 * d + 0.033 * sin(j4) - 0.389 * sin(j5) - 0.082 * sin(j6),
+        ),
+        Satellite::Callisto => PoleAngles::from_deg(
+            268.72 - 0.009 * t - 0.068 * sin(j5) + 0.590 * sin(j6) + 0.010 * sin(j8),
+            64.83 + 0.003 * t - 0.029 * cos(j5) + 0.254 * cos(j6) - 0.004 * cos(j8),
+            259.51 + 21.5710715 * d + 0.061 * sin(j5) - 0.533 * sin(j6) - 0.009 * sin(j8),
+        ),
+        Satellite::Titan => PoleAngles::from_deg(39.4827, 83.4279, 186.5855 + 22.5769768 * d),
+        Satellite::Triton => {
+            let n7 = 177.85 + 52.316 * t;
+            #[allow(clippy::approx_constant)]
+            let ra = [
+                -32.35, -6.28, -2.08, -0.74, -0.28, -0.11, -0.07, -0.02, -0.01,
+            ];
+            let dec = [22.55, 2.10, 0.55, 0.16, 0.05, 0.02, 0.01, 0.0, 0.0];
+            let w = [22.25, 6.73, 2.05, 0.74, 0.28, 0.11, 0.05, 0.02, 0.01];
+            let mut a = (299.36, 41.17, 296.53 - 61.2572637 * d);
+            for (k, ((r, de), w)) in ra.iter().zip(dec.iter()).zip(w.iter()).enumerate() {
+                let n = (k + 1) as f64 * n7;
+                a.0 += r * sin(n);
+                a.1 += de * cos(n);
+                a.2 += w * sin(n);
+            }
+            PoleAngles::from_deg(a.0, a.1, a.2)
+        }
+        Satellite::Charon => PoleAngles::from_deg(132.993, -6.163, 122.695 + 56.3625225 * d),
+    }
+}
+
+/// Lunar principal axis frame to ICRF, from the ephemeris libration angles (phi, theta, psi).
+///
+/// `crate::eph::JPL::moon` returns these alongside the lunar position.
+pub fn moon_pa(librations: &na::Vector3<f64>) -> na::Matrix3<f64> {
+    euler_313(librations.x, librations.y, librations.z)
+}
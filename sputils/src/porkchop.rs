@@ -0,0 +1,274 @@
+use crate::eph::{Body, JPL};
+use crate::lambert::{lambert, LambertError};
+use crate::time::TDB;
+use sofa_sys::DAYSEC;
+
+/// Heliocentric gravitational parameter (km^3/s^2)
+pub const GM_SUN: f64 = crate::bodies::SUN.gm;
+
+/// Circular parking orbit used to turn v-infinity into a burn
+#[derive(Debug, Clone, Copy)]
+pub struct ParkingOrbit {
+    /// Gravitational parameter of the body (km^3/s^2)
+    pub gm: f64,
+    /// Orbit radius (km)
+    pub radius: f64,
+}
+
+impl ParkingOrbit {
+    /// Impulsive burn between this orbit and a hyperbola with excess speed `vinf`
+    pub fn burn(&self, vinf: f64) -> f64 {
+        (vinf * vinf + 2.0 * self.gm / self.radius).sqrt() - (self.gm / self.radius).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PorkchopOptions {
+    /// Gravitational parameter of the central body (km^3/s^2)
+    pub mu: f64,
+    /// Central body the transfer is computed around
+    pub center: Body,
+    pub prograde: bool,
+    /// Complete revolutions to try, the cheapest solution is kept
+    pub max_revs: u32,
+    /// Without a parking orbit, the v-infinity itself is counted as the burn
+    pub departure_orbit: Option<ParkingOrbit>,
+    pub arrival_orbit: Option<ParkingOrbit>,
+}
+
+impl Default for PorkchopOptions {
+    fn default() -> Self {
+        Self {
+            mu: GM_SUN,
+            center: Body::Sun,
+            prograde: true,
+            max_revs: 0,
+            departure_orbit: None,
+            arrival_orbit: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    /// Departure characteristic energy (km^2/s^2)
+    C3,
+    /// Departure hyperbolic excess speed (km/s)
+    VinfDeparture,
+    /// Arrival hyperbolic excess speed (km/s)
+    VinfArrival,
+    /// Total impulsive delta-v (km/s)
+    DeltaV,
+}
+
+/// Transfer costs over a departure/arrival grid.
+///
+/// Every grid is row-major, one row per arrival date and one column per departure date,
+/// so it can be uploaded as-is as a `width` by `height` texture.
+/// Infeasible cells (arrival before departure, solver failure) are NaN.
+#[derive(Debug, Clone)]
+pub struct Porkchop {
+    pub from: Body,
+    pub to: Body,
+    pub departure: Vec<TDB>,
+    pub arrival: Vec<TDB>,
+    pub c3: Vec<f64>,
+    pub vinf_departure: Vec<f64>,
+    pub vinf_arrival: Vec<f64>,
+    pub delta_v: Vec<f64>,
+}
+
+// Evenly spaced epochs, inclusive of both ends
+fn linspace(start: TDB, end: TDB, n: usize) -> Vec<TDB> {
+    let span = (end.0 - start.0) + (end.1 - start.1);
+    (0..n)
+        .map(|i| {
+            let f = if n > 1 {
+                i as f64 / (n - 1) as f64
+            } else {
+                0.0
+            };
+            TDB(start.0, start.1 + span * f)
+        })
+        .collect()
+}
+
+impl Porkchop {
+    /// Errors with `jpl_pleph`'s code when an epoch is outside the ephemeris
+    pub fn compute(
+        jpl: &mut JPL,
+        from: Body,
+        to: Body,
+        departure: (TDB, TDB, usize),
+        arrival: (TDB, TDB, usize),
+        opts: &PorkchopOptions,
+    ) -> Result<Self, i32> {
+        let departure = linspace(departure.0, departure.1, departure.2);
+        let arrival = linspace(arrival.0, arrival.1, arrival.2);
+
+        let dep_states: Vec<_> = departure
+            .iter()
+            .map(|&t| jpl.state(t, from, opts.center))
+            .collect::<Result<_, _>>()?;
+        let arr_states: Vec<_> = arrival
+            .iter()
+            .map(|&t| jpl.state(t, to, opts.center))
+            .collect::<Result<_, _>>()?;
+
+        let n = departure.len() * arrival.len();
+        let mut s = Self {
+            from,
+            to,
+            departure,
+            arrival,
+            c3: vec![f64::NAN; n],
+            vinf_departure: vec![f64::NAN; n],
+            vinf_arrival: vec![f64::NAN; n],
+            delta_v: vec![f64::NAN; n],
+        };
+
+        for (j, (ta, (r2, v2))) in s.arrival.iter().zip(arr_states.iter()).enumerate() {
+            for (i, (td, (r1, v1))) in s.departure.iter().zip(dep_states.iter()).enumerate() {
+                let tof = ((ta.0 - td.0) + (ta.1 - td.1)) * DAYSEC;
+                if let Ok((vd, va, dv)) = best_transfer(r1, v1, r2, v2, tof, opts) {
+                    let k = j * s.departure.len() + i;
+                    s.c3[k] = vd * vd;
+                    s.vinf_departure[k] = vd;
+                    s.vinf_arrival[k] = va;
+                    s.delta_v[k] = dv;
+                }
+            }
+        }
+        Ok(s)
+    }
+
+    pub fn width(&self) -> usize {
+        self.departure.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.arrival.len()
+    }
+
+    pub fn grid(&self, q: Quantity) -> &[f64] {
+        match q {
+            Quantity::C3 => &self.c3,
+            Quantity::VinfDeparture => &self.vinf_departure,
+            Quantity::VinfArrival => &self.vinf_arrival,
+            Quantity::DeltaV => &self.delta_v,
+        }
+    }
+
+    /// Smallest and largest finite value of a grid
+    pub fn range(&self, q: Quantity) -> Option<(f64, f64)> {
+        self.grid(q)
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold(None, |acc, &v| match acc {
+                None => Some((v, v)),
+                Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+            })
+    }
+
+    /// Grid mapped to 0..1 between `lo` and `hi` (clamped), NaN where infeasible
+    pub fn normalized(&self, q: Quantity, lo: f64, hi: f64) -> Vec<f32> {
+        self.grid(q)
+            .iter()
+            .map(|&v| {
+                if v.is_finite() {
+                    ((v - lo) / (hi - lo)).clamp(0.0, 1.0) as f32
+                } else {
+                    f32::NAN
+                }
+            })
+            .collect()
+    }
+
+    /// Cheapest cell by total delta-v, as (departure index, arrival index)
+    pub fn minimum(&self) -> Option<(usize, usize)> {
+        let w = self.width();
+        self.delta_v
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.is_finite())
+            .min_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .map(|(k, _)| (k % w, k / w))
+    }
+
+    /// One line per cell, dates as TDB Julian dates
+    pub fn write_csv<W: std::io::Write>(&self, mut w: W) -> std::io::Result<()> {
+        writeln!(
+            w,
+            "departure_jd,arrival_jd,tof_days,c3_km2_s2,vinf_departure_km_s,vinf_arrival_km_s,delta_v_km_s"
+        )?;
+        for (j, ta) in self.arrival.iter().enumerate() {
+            for (i, td) in self.departure.iter().enumerate() {
+                let k = j * self.width() + i;
+                writeln!(
+                    w,
+                    "{:.6},{:.6},{:.6},{},{},{},{}",
+                    td.0 + td.1,
+                    ta.0 + ta.1,
+                    (ta.0 - td.0) + (ta.1 - td.1),
+                    self.c3[k],
+                    self.vinf_departure[k],
+                    self.vinf_arrival[k],
+                    self.delta_v[k],
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Returns (departure v-infinity, arrival v-infinity, total delta-v) of the cheapest solution
+fn best_transfer(
+    r1: &na::Vector3<f64>,
+    v1: &na::Vector3<f64>,
+    r2: &na::Vector3<f64>,
+    v2: &na::Vector3<f64>,
+    tof: f64,
+    opts: &PorkchopOptions,
+) -> Result<(f64, f64, f64), LambertError> {
+    let sols = lambert(r1, r2, tof, opts.mu, opts.prograde, opts.max_revs)?;
+    sols.iter()
+        .map(|s| {
+            let vd = (s.v1 - v1).norm();
+            let va = (s.v2 - v2).norm();
+            let dv = opts.departure_orbit.map_or(vd, |o| o.burn(vd))
+                + opts.arrival_orbit.map_or(va, |o| o.burn(va));
+            (vd, va, dv)
+        })
+        .filter(|t| t.2.is_finite())
+        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+        .ok_or(LambertError::NoSolution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimum_skips_infeasible() {
+        let t = |d: f64| TDB(2451545.0, d);
+        let grid = |v: [f64; 6]| v.to_vec();
+        let p = Porkchop {
+            from: Body::Earth,
+            to: Body::Mars,
+            departure: vec![t(0.0), t(10.0), t(20.0)],
+            arrival: vec![t(200.0), t(300.0)],
+            c3: grid([f64::NAN; 6]),
+            vinf_departure: grid([f64::NAN; 6]),
+            vinf_arrival: grid([f64::NAN; 6]),
+            delta_v: grid([5.0, f64::NAN, 7.0, 6.0, 4.5, f64::NAN]),
+        };
+        // Row-major by arrival, so index 4 is the second departure and arrival
+        assert_eq!(p.minimum(), Some((1, 1)));
+
+        let none = Porkchop {
+            delta_v: grid([f64::NAN; 6]),
+            ..p
+        };
+        assert_eq!(none.minimum(), None);
+    }
+}
@@ -0,0 +1,131 @@
+// Root and extremum finding over a scalar function of time
+// Times are plain f64 (usually days relative to some epoch) so callers choose their own scale
+
+const GOLDEN: f64 = 0.618_033_988_749_894_8;
+
+/// Refine a sign change of `f` between `a` and `b` down to `tol`
+pub fn bisect<F: FnMut(f64) -> f64>(mut f: F, mut a: f64, mut b: f64, tol: f64) -> f64 {
+    let mut fa = f(a);
+    while (b - a).abs() > tol {
+        let m = 0.5 * (a + b);
+        let fm = f(m);
+        if (fm < 0.0) == (fa < 0.0) {
+            a = m;
+            fa = fm;
+        } else {
+            b = m;
+        }
+    }
+    0.5 * (a + b)
+}
+
+/// Every sign change of `f` in `a..b`, sampled every `step` and refined to `tol`.
+///
+/// The flag is true where `f` goes from negative to positive.
+/// Roots closer together than `step` can be missed.
+pub fn roots<F: FnMut(f64) -> f64>(
+    mut f: F,
+    a: f64,
+    b: f64,
+    step: f64,
+    tol: f64,
+) -> Vec<(f64, bool)> {
+    let mut v = Vec::new();
+    let mut t0 = a;
+    let mut f0 = f(t0);
+    while t0 < b {
+        let t1 = (t0 + step).min(b);
+        let f1 = f(t1);
+        if (f0 < 0.0) != (f1 < 0.0) {
+            v.push((bisect(&mut f, t0, t1, tol), f0 < 0.0));
+        }
+        t0 = t1;
+        f0 = f1;
+    }
+    v
+}
+
+/// Golden section search for the minimum of a unimodal `f` in `a..b`
+pub fn minimize<F: FnMut(f64) -> f64>(mut f: F, mut a: f64, mut b: f64, tol: f64) -> f64 {
+    let mut c = b - GOLDEN * (b - a);
+    let mut d = a + GOLDEN * (b - a);
+    let mut fc = f(c);
+    let mut fd = f(d);
+    while (b - a).abs() > tol {
+        if fc < fd {
+            b = d;
+            d = c;
+            fd = fc;
+            c = b - GOLDEN * (b - a);
+            fc = f(c);
+        } else {
+            a = c;
+            c = d;
+            fc = fd;
+            d = a + GOLDEN * (b - a);
+            fd = f(d);
+        }
+    }
+    0.5 * (a + b)
+}
+
+/// Every local minimum of `f` in `a..b`, sampled every `step` and refined to `tol`
+pub fn minima<F: FnMut(f64) -> f64>(mut f: F, a: f64, b: f64, step: f64, tol: f64) -> Vec<f64> {
+    let mut v = Vec::new();
+    let mut t0 = a;
+    let mut f0 = f(t0);
+    let mut t1 = (a + step).min(b);
+    let mut f1 = f(t1);
+    while t1 < b {
+        let t2 = (t1 + step).min(b);
+        let f2 = f(t2);
+        if f1 <= f0 && f1 < f2 {
+            v.push(minimize(&mut f, t0, t2, tol));
+        }
+        t0 = t1;
+        f0 = f1;
+        t1 = t2;
+        f1 = f2;
+    }
+    v
+}
+
+/// Every local maximum of `f` in `a..b`, sampled every `step` and refined to `tol`
+pub fn maxima<F: FnMut(f64) -> f64>(mut f: F, a: f64, b: f64, step: f64, tol: f64) -> Vec<f64> {
+    minima(|t| -f(t), a, b, step, tol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn bracketed_root() {
+        let t = bisect(f64::cos, 1.0, 2.0, 1e-12);
+        assert!((t - PI / 2.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn roots_and_direction() {
+        let r = roots(f64::sin, 1.0, 10.0, 0.5, 1e-10);
+        assert_eq!(r.len(), 3);
+        for (k, &(t, rising)) in r.iter().enumerate() {
+            assert!((t - (k + 1) as f64 * PI).abs() < 1e-8);
+            // Falling through PI and 3 PI, rising through 2 PI
+            assert_eq!(rising, k == 1);
+        }
+    }
+
+    #[test]
+    fn extrema() {
+        let t = minimize(|t| (t - 2.0).powi(2), 0.0, 5.0, 1e-10);
+        assert!((t - 2.0).abs() < 1e-8);
+        let lows = minima(f64::cos, 0.0, 13.0, 0.5, 1e-10);
+        assert_eq!(lows.len(), 2);
+        assert!((lows[0] - PI).abs() < 1e-6 && (lows[1] - 3.0 * PI).abs() < 1e-6);
+        let highs = maxima(f64::cos, 0.5, 13.0, 0.5, 1e-10);
+        assert_eq!(highs.len(), 2);
+        assert!((highs[0] - 2.0 * PI).abs() < 1e-6 && (highs[1] - 4.0 * PI).abs() < 1e-6);
+    }
+}
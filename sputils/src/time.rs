@@ -191,6 +191,16 @@ impl TT {
             Self(a, b)
         }
     }
+
+    /// Besselian epoch such as 1875.0, used by older catalogues
+    pub fn from_besselian(epoch: f64) -> Self {
+        unsafe {
+            let mut a: f64 = 0.0;
+            let mut b: f64 = 0.0;
+            iauEpb2jd(epoch, &mut a, &mut b);
+            Self(a, b)
+        }
+    }
 }
 
 impl TDB {
@@ -37,14 +37,6 @@ fn get_iss_pos(et: f64) -> na::Vector3<f64> {
     na::Vector3::new(t[0], t[1], t[2]) / 1000.0
 }
 
-fn get_iss_line(et: f64) -> Vec<na::Vector3<f32>> {
-    let mut v = Vec::with_capacity(60 * 60);
-    for x in -(60 * 60)..(60 * 60) {
-        v.push(na::convert(get_iss_pos(et + (x as f64))))
-    }
-    v
-}
-
 fn get_sun_pos(et: f64) -> na::Vector3<f64> {
     // This is km
     let (t, _) = rspice::spkpos("SUN", et, "J2000", "NONE", "EARTH");
@@ -56,95 +48,162 @@ fn main() {
     // avoid crash when data ran out (everything not just iss)
     // Use error checking version not hacky one
     // Verify position of everything (sun and moon) by looking at eclipse
-    // Oh and stars
     rspice::furnsh("../cspice/kernels/all.tm");
     use imgui::im_str;
     let mut sd = StarDome::new().unwrap();
+    // From the ISS (1 m) out past the Moon
+    let depth = sd.cam.set_depth(stardome::DepthMode::ReversedZ, &sd.graphics);
+    if depth != stardome::DepthMode::ReversedZ {
+        eprintln!("No clip control for reversed-Z depth, using {:?}", depth);
+    }
+    sd.cam.set_clipping_planes(1e-6, 1e7);
 
     let mut win = true;
-    let mut pos = [sd.cam.position.x, sd.cam.position.y, sd.cam.position.z];
-    let mut rx = sd.cam.rx;
-    let mut ry = sd.cam.ry;
-    let mut rz = sd.cam.rz;
     let mut fov = sd.cam.get_fov().to_radians();
 
-    let beninging = std::time::Instant::now();
     //let (djmjd0, tt, date, tut) = sputils::get_mjd(2020, 12, 10, 8, 0, 0.0, -0.2).unwrap();
     let et = rspice::str2et("2021-01-18T12:00:00");
-
-    let mut earth = stardome::Planet {
-        r_equatorial: 6.3781,
-        r_polar: 6.3568,
-        lighting: true,
-        texture: stardome::Texture::open("img/gen/earth.png").unwrap(),
-        atm: Some(stardome::Atmosphere {
-            offset: 0.06,
-            sun_intensity: 20.0,
-            scale_height_r: 7994.0,
-            scatter_coeff_r: na::Vector3::new(3.8e-6, 13.5e-6, 33.1e-6),
-            scale_height_m: 1200.0,
-            scatter_coeff_m: na::Vector3::from_element(21e-6),
-            asymmetry_m: 0.76,
-        }),
-        clouds: None,
-        tf: get_mat(et),
-    };
-
-    // TODO spice provides constants get it from them
-    let mut moon = stardome::Planet {
-        r_equatorial: 1.7381,
-        r_polar: 1.736,
-        lighting: true,
-        texture: stardome::Texture::open("img/gen/moon.png").unwrap(),
-        atm: None,
-        clouds: None,
-        tf: get_moon_mat(et),
-    };
-
-    let mut iss_label = stardome::Text {
-        position: na::Vector3::zeros(),
-        color: 0xFF00FF80,
-        text: "International Space Station".to_string(),
-    };
-
-    let mut moon_label = stardome::Text {
-        position: na::Vector3::zeros(),
-        color: 0xFFFFFF20,
-        text: "Moon".to_string(),
-    };
-
-    let mut test_line = stardome::Points::new(0xABCDEFFF, 4.0, true, vec![na::Vector3::zeros(); 2]);
-    let mut sun_line = stardome::Points::new(0xFF8000FF, 4.0, true, vec![na::Vector3::zeros(); 2]);
+    // ET is TDB seconds past J2000
+    sd.clock
+        .set_epoch(sputils::time::TDB(2451545.0, et / 86400.0));
+    sd.clock.warp = 60.0;
+
+    // Geocentric J2000
+    let scene = &mut sd.scene;
+    let earth_fixed = scene.add(scene.root(), "Earth-fixed", get_mat(et));
+    let moon_frame = scene.add(scene.root(), "Moon", get_moon_mat(et));
+    let iss_frame = scene.add(
+        scene.root(),
+        "ISS",
+        na::Matrix4::new_translation(&get_iss_pos(et)),
+    );
+
+    let mut earth = stardome::Planet::new(
+        &sputils::bodies::EARTH,
+        stardome::Texture::open("img/gen/earth.png").unwrap(),
+    );
+    if let Ok(t) = stardome::Texture::open("img/gen/earth_night.png") {
+        earth = earth.with_night(t);
+    }
+    earth.clouds = stardome::Texture::open("img/gen/earth_clouds.png")
+        .ok()
+        .map(stardome::Clouds::new);
+    // Geodetic tiles from gdal2tiles or similar, missing ones come from lower zooms
+    if std::path::Path::new("img/tiles/earth").is_dir() {
+        let elevation = if std::path::Path::new("img/tiles/earth_height").is_dir() {
+            let tiles = stardome::TileSource::new("img/tiles/earth_height");
+            Some(stardome::Elevation::new(tiles, -11.0, 8.9))
+        } else {
+            None
+        };
+        earth = earth.with_terrain(stardome::TileSource::new("img/tiles/earth"), elevation);
+    }
+    scene.attach(earth_fixed, earth);
+    let mut moon = stardome::Planet::new(
+        &sputils::bodies::MOON,
+        stardome::Texture::open("img/gen/moon.png").unwrap(),
+    );
+    // LOLA elevations, black and white at the lowest and highest points
+    if let Ok(t) = stardome::Texture::open("img/gen/moon_height.png") {
+        let mut relief = stardome::Relief::new(t, -9.13, 10.79);
+        relief.exaggeration = 10.0;
+        moon.relief = Some(relief);
+    }
+    scene.attach(moon_frame, moon);
+    scene.attach(
+        moon_frame,
+        stardome::Text {
+            position: na::Vector3::zeros(),
+            color: 0xFFFFFF20,
+            text: "Moon".to_string(),
+        },
+    );
     let mut iss = stardome::Points::new(0xFF00FF80, 8.0, false, vec![na::Vector3::zeros()]);
-    let mut orbit = stardome::Points::new(0x00FF0080, 1.0, true, get_iss_line(et));
+    iss.marker = stardome::Marker::Disc;
+    scene.attach(iss_frame, iss);
+    scene.attach(
+        iss_frame,
+        stardome::Text {
+            position: na::Vector3::zeros(),
+            color: 0xFF00FF80,
+            text: "International Space Station".to_string(),
+        },
+    );
+
+    let test_line = scene.insert(stardome::Points::new(
+        0xABCDEFFF,
+        4.0,
+        true,
+        vec![na::Vector3::zeros(); 2],
+    ));
+    let sun_line = scene.insert(stardome::Points::new(
+        0xFF8000FF,
+        4.0,
+        true,
+        vec![na::Vector3::zeros(); 2],
+    ));
+    match stardome::StarField::open("data/hip_main.dat", stardome::CatalogFormat::Hipparcos) {
+        Ok(stars) => {
+            match stardome::ConstellationLines::open("data/constellationship.fab", stars.stars()) {
+                Ok(lines) => {
+                    let mut names = stardome::ConstellationNames::from_lines(&lines);
+                    let _ = names.open_names("data/constellation_names.eng.fab");
+                    scene.insert(lines);
+                    scene.insert(names);
+                }
+                Err(e) => eprintln!("No constellations: {}", e),
+            }
+            scene.insert(stars);
+        }
+        Err(e) => eprintln!("No stars: {}", e),
+    }
+    if let Ok(bounds) = stardome::ConstellationBoundaries::open("data/bound_18.dat") {
+        scene.insert(bounds);
+    }
+    let equatorial = scene.insert(stardome::Grid::new(stardome::GridFrame::Equatorial));
+    // Greenwich
+    let horizon = scene.insert(stardome::Grid::new(stardome::GridFrame::Horizon(
+        sputils::coord::Observer {
+            lon: 0.0,
+            lat: 51.4769_f64.to_radians(),
+            height: 46.0,
+        },
+    )));
+    let (mut show_equatorial, mut show_horizon) = (true, false);
+    // An hour either side, fading behind
+    scene.insert(stardome::OrbitPath::new(
+        0x00FF0080,
+        get_iss_pos,
+        60.0 * 60.0,
+        60.0 * 60.0,
+    ));
     loop {
-        sd.sun = get_sun_pos(et);
-        let tw = beninging.elapsed().as_secs_f64() * 60.0;
-        // This makes some things a bit problematic because of borrowing
-        // Consider using this for imgui only, and having rest of stuff just be functions
-        sd.draw(&mut earth);
-        sd.draw(&mut moon);
-        test_line.modify_points(|p| {
+        // The controller may have moved the camera
+        let mut pos = [
+            sd.cam.position.x as f32,
+            sd.cam.position.y as f32,
+            sd.cam.position.z as f32,
+        ];
+        let (mut rx, mut ry, mut rz) = sd.cam.angles();
+        let mut changed = false;
+        let t = sd.clock.seconds_since_j2000();
+        sd.sun = get_sun_pos(t);
+        let sun_dir = sd.get_sun_dir();
+        let scene = &mut sd.scene;
+        scene.set_local(earth_fixed, get_mat(t));
+        scene.set_local(moon_frame, get_moon_mat(t));
+        scene.set_position(iss_frame, &get_iss_pos(t));
+        scene.get_mut(test_line).modify_points(|p| {
             p[1].copy_from(&na::convert::<na::Vector3<f64>, na::Vector3<f32>>(
-                get_moon_pos(et + tw),
+                get_moon_pos(t),
             ));
         });
-        sun_line.modify_points(|p| {
-            p[1] = sd.get_sun_dir() * 100.0;
+        scene.get_mut(sun_line).modify_points(|p| {
+            p[1] = sun_dir * 100.0;
         });
-        iss.modify_points(|p| {
-            p[0].copy_from(&na::convert::<na::Vector3<f64>, na::Vector3<f32>>(
-                get_iss_pos(et + tw),
-            ));
-        });
-        iss_label.position = na::convert(iss.get_points()[0].clone_owned());
-        moon_label.position = na::convert(get_moon_pos(et + tw));
-        sd.draw(&mut test_line);
-        sd.draw(&mut iss);
-        sd.draw(&mut orbit);
-        sd.draw(&mut iss_label);
-        sd.draw(&mut moon_label);
-        sd.draw(&mut sun_line);
+        scene.get_mut(equatorial).visible = show_equatorial;
+        scene.get_mut(horizon).visible = show_horizon;
+        let moon = scene.get(test_line).get_points()[1];
 
         if sd
             .frame(|ui| {
@@ -154,15 +213,16 @@ fn main() {
                 imgui::Window::new(im_str!("Camera"))
                     .size([420.0, 250.0], imgui::Condition::FirstUseEver)
                     .build(&ui, || {
-                        imgui::InputFloat3::new(&ui, im_str!("Position"), &mut pos).build();
+                        changed |=
+                            imgui::InputFloat3::new(&ui, im_str!("Position"), &mut pos).build();
                         ui.separator();
-                        imgui::AngleSlider::new(im_str!("RX"))
+                        changed |= imgui::AngleSlider::new(im_str!("RX"))
                             .min_degrees(0.0)
                             .build(&ui, &mut rx);
-                        imgui::AngleSlider::new(im_str!("RY"))
+                        changed |= imgui::AngleSlider::new(im_str!("RY"))
                             .min_degrees(0.0)
                             .build(&ui, &mut ry);
-                        imgui::AngleSlider::new(im_str!("RZ"))
+                        changed |= imgui::AngleSlider::new(im_str!("RZ"))
                             .min_degrees(0.0)
                             .build(&ui, &mut rz);
                         ui.separator();
@@ -172,22 +232,22 @@ fn main() {
                             .flags(imgui::SliderFlags::ALWAYS_CLAMP)
                             .build(&ui, &mut fov);
 
-                        ui.text(format!("Moon: {:?}", test_line.get_points()[1].as_slice()));
-                        ui.text(format!("Time: {}", et + tw));
+                        ui.checkbox(im_str!("Equatorial grid"), &mut show_equatorial);
+                        ui.checkbox(im_str!("Horizon grid"), &mut show_horizon);
+                        ui.text(format!("Moon: {:?}", moon.as_slice()));
+                        ui.text(format!("ET: {}", t));
                     });
             })
             .is_err()
         {
             break;
         }
-        sd.cam.position.x = pos[0];
-        sd.cam.position.y = pos[1];
-        sd.cam.position.z = pos[2];
-        sd.cam.rx = rx;
-        sd.cam.ry = ry;
-        sd.cam.rz = rz;
+        if changed {
+            sd.cam.position.x = pos[0] as f64;
+            sd.cam.position.y = pos[1] as f64;
+            sd.cam.position.z = pos[2] as f64;
+            sd.cam.set_angles(rx, ry, rz);
+        }
         sd.cam.set_fov(fov.to_degrees());
-        earth.tf = get_mat(et + tw);
-        moon.tf = get_moon_mat(et + tw);
     }
 }
@@ -0,0 +1,243 @@
+// Simulation time, advanced by StarDome::frame and passed to drawables
+use sputils::time::{TimeError, TAI, TDB, TT, UTC};
+use sofa_sys::{DAYSEC, DJ00};
+use std::convert::TryFrom;
+
+pub struct SimClock {
+    // Kept as J2000 + days for precision
+    epoch: TDB,
+    /// Simulated seconds per real second, negative runs backwards
+    pub warp: f64,
+    pub paused: bool,
+    /// Simulated seconds moved by each `step`
+    pub step_size: f64,
+}
+
+impl SimClock {
+    pub fn new(epoch: TDB) -> Self {
+        Self {
+            epoch: normalize(epoch),
+            warp: 1.0,
+            paused: false,
+            step_size: 60.0,
+        }
+    }
+
+    /// Real time, from the system clock, which may be set outside the range of dates SOFA
+    /// accepts
+    pub fn now() -> Result<Self, TimeError> {
+        let utc = UTC::from_system_time(std::time::SystemTime::now());
+        Ok(Self::new(utc_to_tdb(utc)?))
+    }
+
+    pub fn epoch(&self) -> TDB {
+        self.epoch
+    }
+
+    pub fn set_epoch(&mut self, epoch: TDB) {
+        self.epoch = normalize(epoch);
+    }
+
+    pub fn jump_to(&mut self, utc: UTC) -> Result<(), TimeError> {
+        self.set_epoch(utc_to_tdb(utc)?);
+        Ok(())
+    }
+
+    /// Back to the system clock at normal speed
+    pub fn jump_to_now(&mut self) -> Result<(), TimeError> {
+        *self = Self {
+            step_size: self.step_size,
+            ..Self::now()?
+        };
+        Ok(())
+    }
+
+    pub fn utc(&self) -> Result<UTC, TimeError> {
+        // TDB-TT is under 2 ms
+        sputils::almanac::tt_to_utc(self.epoch.into_tt(0.0))
+    }
+
+    pub fn tt(&self) -> TT {
+        self.epoch.into_tt(0.0)
+    }
+
+    /// TDB seconds since J2000, the same as SPICE ephemeris time
+    pub fn seconds_since_j2000(&self) -> f64 {
+        seconds_since_j2000(self.epoch)
+    }
+
+    /// Move on by `dt` real seconds, unless paused
+    pub fn advance(&mut self, dt: f64) {
+        if !self.paused {
+            self.add_seconds(dt * self.warp);
+        }
+    }
+
+    /// Move `n` steps of `step_size`, whether paused or not
+    pub fn step(&mut self, n: i32) {
+        self.add_seconds(n as f64 * self.step_size);
+    }
+
+    pub fn add_seconds(&mut self, s: f64) {
+        self.epoch.1 += s / DAYSEC;
+    }
+
+    pub fn reverse(&mut self) {
+        self.warp = -self.warp;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Date entry, pause, warp and stepping controls
+    #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+    pub fn panel(&mut self, ui: &imgui::Ui, state: &mut ClockPanel) {
+        use imgui::im_str;
+        if !state.open {
+            return;
+        }
+        let mut open = state.open;
+        imgui::Window::new(im_str!("Time"))
+            .size([360.0, 220.0], imgui::Condition::FirstUseEver)
+            .opened(&mut open)
+            .build(ui, || {
+                match self.utc() {
+                    Ok(utc) => ui.text(format!("{}", utc)),
+                    Err(e) => ui.text(format!("{}", e)),
+                }
+                ui.text(format!("ET {:.3} s", self.seconds_since_j2000()));
+                ui.separator();
+
+                if ui.button(
+                    if self.paused {
+                        im_str!("Play")
+                    } else {
+                        im_str!("Pause")
+                    },
+                    [0.0, 0.0],
+                ) {
+                    self.toggle_pause();
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Reverse"), [0.0, 0.0]) {
+                    self.reverse();
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Real time"), [0.0, 0.0]) {
+                    self.warp = 1.0;
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Now"), [0.0, 0.0]) {
+                    state.error = self.jump_to_now().err().map(|e| e.to_string());
+                }
+
+                let mut warp = self.warp as f32;
+                if ui.input_float(im_str!("Warp"), &mut warp).build() {
+                    self.warp = warp as f64;
+                }
+                for &w in &[1.0_f64, 60.0, 3600.0, 86400.0] {
+                    if ui.small_button(&imgui::ImString::new(format!("x{}", w))) {
+                        self.warp = w.copysign(self.warp);
+                    }
+                    ui.same_line(0.0);
+                }
+                ui.new_line();
+                ui.separator();
+
+                let mut step = self.step_size as f32;
+                if ui.input_float(im_str!("Step (s)"), &mut step).build() {
+                    self.step_size = step as f64;
+                }
+                ui.input_int(im_str!("Steps"), &mut state.steps).build();
+                if ui.button(im_str!("Back"), [0.0, 0.0]) {
+                    self.step(-state.steps);
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Forward"), [0.0, 0.0]) {
+                    self.step(state.steps);
+                }
+                ui.separator();
+
+                ui.input_text(im_str!("UTC"), &mut state.date).build();
+                if ui.button(im_str!("Jump"), [0.0, 0.0]) {
+                    state.error = match parse_utc(state.date.to_str()) {
+                        Some(utc) => self.jump_to(utc).err().map(|e| e.to_string()),
+                        None => Some("Expected YYYY-MM-DD HH:MM:SS".to_string()),
+                    };
+                }
+                if let Some(e) = &state.error {
+                    ui.same_line(0.0);
+                    ui.text_colored([1.0, 0.3, 0.3, 1.0], e);
+                }
+            });
+        state.open = open;
+    }
+}
+
+impl Default for SimClock {
+    /// Now, or J2000 when the system clock is out of range
+    fn default() -> Self {
+        Self::now().unwrap_or_else(|_| Self::new(TDB(DJ00, 0.0)))
+    }
+}
+
+/// State of `SimClock::panel`
+#[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+pub struct ClockPanel {
+    pub open: bool,
+    steps: i32,
+    date: imgui::ImString,
+    error: Option<String>,
+}
+
+#[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+impl Default for ClockPanel {
+    fn default() -> Self {
+        let mut date = imgui::ImString::with_capacity(32);
+        date.push_str("2000-01-01 12:00:00");
+        Self {
+            open: true,
+            steps: 1,
+            date,
+            error: None,
+        }
+    }
+}
+
+pub fn utc_to_tdb(utc: UTC) -> Result<TDB, TimeError> {
+    Ok(TDB::from_tt(TT::from(TAI::try_from(utc)?), 0.0))
+}
+
+/// `YYYY-MM-DD`, optionally followed by `HH:MM[:SS.sss]` after a space or `T`, and a `Z`
+pub fn parse_utc(s: &str) -> Option<UTC> {
+    let s = s.trim().trim_end_matches('Z');
+    let (date, time) = match s.find(&['T', ' '][..]) {
+        Some(i) => (&s[..i], s[i + 1..].trim()),
+        None => (s, ""),
+    };
+    let mut d = date.splitn(3, '-');
+    let y = d.next()?.parse().ok()?;
+    let m = d.next()?.parse().ok()?;
+    let d = d.next()?.parse().ok()?;
+    if time.is_empty() {
+        return UTC::from_ymdhms(y, m, d, 0, 0, 0.0);
+    }
+    let mut t = time.splitn(3, ':');
+    let hour = t.next()?.parse().ok()?;
+    let minute = t.next()?.parse().ok()?;
+    let second = match t.next() {
+        Some(s) => s.parse().ok()?,
+        None => 0.0,
+    };
+    UTC::from_ymdhms(y, m, d, hour, minute, second)
+}
+
+/// TDB seconds since J2000 (SPICE ephemeris time) of any epoch
+pub fn seconds_since_j2000(t: TDB) -> f64 {
+    ((t.0 - DJ00) + t.1) * DAYSEC
+}
+
+fn normalize(t: TDB) -> TDB {
+    TDB(DJ00, (t.0 - DJ00) + t.1)
+}
@@ -0,0 +1,231 @@
+// Lookup tables for the atmosphere shader, after Hillaire, "A Scalable and Production Ready
+// Sky and Atmosphere Rendering Technique" (2020), with the transmittance parametrisation of
+// Bruneton and Neyret, "Precomputed Atmospheric Scattering" (2008)
+// Distances are in metres, like the scattering coefficients
+use super::texture::Texture;
+use std::f64::consts::PI;
+
+// Texels along mu and r
+const TRANSMITTANCE_SIZE: (usize, usize) = (256, 64);
+// Texels along mu_s and r
+const MULTISCATTERING_SIZE: usize = 32;
+const TRANSMITTANCE_STEPS: usize = 40;
+const MULTISCATTERING_STEPS: usize = 20;
+// Directions around each point are this many bands by this many sectors of equal area
+const DIRECTIONS: usize = 8;
+// Mie absorbs as well as scatters, the same in the shader
+const MIE_EXTINCTION_MUL: f64 = 1.1;
+// Light the ground reflects back into the atmosphere
+const GROUND_ALBEDO: f64 = 0.3;
+const ISOTROPIC: f64 = 1.0 / (4.0 * PI);
+
+type Rgb = na::Vector3<f64>;
+
+/// Everything the tables depend on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Medium {
+    pub r_ground: f64,
+    pub r_top: f64,
+    pub scale_height_r: f64,
+    pub scatter_r: Rgb,
+    pub scale_height_m: f64,
+    pub scatter_m: Rgb,
+}
+
+impl Medium {
+    // Scattering and extinction at radius r
+    fn coefficients(&self, r: f64) -> (Rgb, Rgb) {
+        let h = (r - self.r_ground).max(0.0);
+        let rayleigh = self.scatter_r * (-h / self.scale_height_r).exp();
+        let mie = self.scatter_m * (-h / self.scale_height_m).exp();
+        (rayleigh + mie, rayleigh + mie * MIE_EXTINCTION_MUL)
+    }
+
+    // Along a ray from radius r, mu is the cosine of its angle from the zenith
+    fn distance_to_top(&self, r: f64, mu: f64) -> f64 {
+        let d = r * r * (mu * mu - 1.0) + self.r_top * self.r_top;
+        (-r * mu + d.max(0.0).sqrt()).max(0.0)
+    }
+
+    fn distance_to_ground(&self, r: f64, mu: f64) -> f64 {
+        let d = r * r * (mu * mu - 1.0) + self.r_ground * self.r_ground;
+        (-r * mu - d.max(0.0).sqrt()).max(0.0)
+    }
+
+    fn hits_ground(&self, r: f64, mu: f64) -> bool {
+        mu < 0.0 && r * r * (mu * mu - 1.0) + self.r_ground * self.r_ground >= 0.0
+    }
+
+    // Position in the transmittance table (0 to 1) of a ray that reaches the top
+    fn transmittance_uv(&self, r: f64, mu: f64) -> (f64, f64) {
+        let h = (self.r_top.powi(2) - self.r_ground.powi(2)).sqrt();
+        let rho = (r * r - self.r_ground.powi(2)).max(0.0).sqrt();
+        let (d_min, d_max) = (self.r_top - r, rho + h);
+        let d = self.distance_to_top(r, mu);
+        ((d - d_min) / (d_max - d_min), rho / h)
+    }
+
+    // Inverse of `transmittance_uv`
+    fn transmittance_ray(&self, u: f64, v: f64) -> (f64, f64) {
+        let h = (self.r_top.powi(2) - self.r_ground.powi(2)).sqrt();
+        let rho = h * v;
+        let r = (rho * rho + self.r_ground.powi(2)).sqrt();
+        let (d_min, d_max) = (self.r_top - r, rho + h);
+        let d = d_min + u * (d_max - d_min);
+        let mu = if d == 0.0 {
+            1.0
+        } else {
+            ((h * h - rho * rho - d * d) / (2.0 * r * d)).clamp(-1.0, 1.0)
+        };
+        (r, mu)
+    }
+
+    // Sunlight reaching radius r with the sun at cosine mu from the zenith
+    fn transmittance(&self, table: &Table, r: f64, mu: f64) -> Rgb {
+        if self.hits_ground(r, mu) {
+            return Rgb::zeros();
+        }
+        let (u, v) = self.transmittance_uv(r, mu);
+        table.sample(u, v)
+    }
+}
+
+// Rows from v = 0, filtered like a GL texture
+struct Table {
+    width: usize,
+    height: usize,
+    data: Vec<Rgb>,
+}
+
+impl Table {
+    // 0 and 1 are the centres of the first and last texels
+    fn sample(&self, u: f64, v: f64) -> Rgb {
+        let x = u.clamp(0.0, 1.0) * (self.width - 1) as f64;
+        let y = v.clamp(0.0, 1.0) * (self.height - 1) as f64;
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+        let at = |x: usize, y: usize| self.data[y * self.width + x];
+        (at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx) * (1.0 - fy)
+            + (at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx) * fy
+    }
+
+    fn texture(&self) -> Texture {
+        let mut rgb = Vec::with_capacity(3 * self.data.len());
+        for c in &self.data {
+            rgb.extend_from_slice(&[c.x as f32, c.y as f32, c.z as f32]);
+        }
+        Texture::from_rgb_f32(self.width, self.height, &rgb)
+    }
+}
+
+// Transmittance from every height and angle to the top of the atmosphere
+fn transmittance_table(m: &Medium) -> Table {
+    let (width, height) = TRANSMITTANCE_SIZE;
+    let mut data = Vec::with_capacity(width * height);
+    for j in 0..height {
+        for i in 0..width {
+            let (u, v) = (
+                i as f64 / (width - 1) as f64,
+                j as f64 / (height - 1) as f64,
+            );
+            let (r, mu) = m.transmittance_ray(u, v);
+            let dt = m.distance_to_top(r, mu) / TRANSMITTANCE_STEPS as f64;
+            let mut depth = Rgb::zeros();
+            for k in 0..TRANSMITTANCE_STEPS {
+                let t = (k as f64 + 0.5) * dt;
+                let rk = (r * r + t * t + 2.0 * r * mu * t).sqrt();
+                depth += m.coefficients(rk).1 * dt;
+            }
+            data.push(depth.map(|x| (-x).exp()));
+        }
+    }
+    Table {
+        width,
+        height,
+        data,
+    }
+}
+
+// Light scattered two or more times, for a unit of sunlight and unit scattering coefficient.
+//
+// The second order is found by marching in every direction with isotropic scattering, and the
+// fraction of light that is scattered again makes the higher orders a geometric series
+fn multiscattering_table(m: &Medium, transmittance: &Table) -> Table {
+    let n = MULTISCATTERING_SIZE;
+    let mut data = Vec::with_capacity(n * n);
+    for j in 0..n {
+        let r = m.r_ground + j as f64 / (n - 1) as f64 * (m.r_top - m.r_ground);
+        let origin = Rgb::new(0.0, 0.0, r);
+        for i in 0..n {
+            let mu_s = i as f64 / (n - 1) as f64 * 2.0 - 1.0;
+            let sun = Rgb::new((1.0 - mu_s * mu_s).max(0.0).sqrt(), 0.0, mu_s);
+            let mut second = Rgb::zeros();
+            let mut transfer = Rgb::zeros();
+            for band in 0..DIRECTIONS {
+                let mu = 1.0 - 2.0 * (band as f64 + 0.5) / DIRECTIONS as f64;
+                let sin = (1.0 - mu * mu).sqrt();
+                for sector in 0..DIRECTIONS {
+                    let phi = 2.0 * PI * (sector as f64 + 0.5) / DIRECTIONS as f64;
+                    let dir = Rgb::new(sin * phi.cos(), sin * phi.sin(), mu);
+                    let ground = m.hits_ground(r, mu);
+                    let d = if ground {
+                        m.distance_to_ground(r, mu)
+                    } else {
+                        m.distance_to_top(r, mu)
+                    };
+                    let dt = d / MULTISCATTERING_STEPS as f64;
+                    let mut throughput = Rgb::repeat(1.0);
+                    for k in 0..MULTISCATTERING_STEPS {
+                        let p = origin + dir * ((k as f64 + 0.5) * dt);
+                        let rk = p.norm();
+                        let (scatter, extinction) = m.coefficients(rk);
+                        let extinction = extinction.map(|e| e.max(1e-20));
+                        let step = extinction.map(|e| (-e * dt).exp());
+                        // Integrated over the step rather than sampled, stable with few steps
+                        let integral = (Rgb::repeat(1.0) - step).component_div(&extinction);
+                        let scattered = throughput.component_mul(&scatter.component_mul(&integral));
+                        let sunlight = m.transmittance(transmittance, rk, p.dot(&sun) / rk);
+                        second += scattered.component_mul(&sunlight) * ISOTROPIC;
+                        transfer += scattered;
+                        throughput = throughput.component_mul(&step);
+                    }
+                    if ground {
+                        let up = (origin + dir * d).normalize();
+                        let mu_g = up.dot(&sun);
+                        let sunlight = m.transmittance(transmittance, m.r_ground, mu_g);
+                        second += throughput.component_mul(&sunlight)
+                            * (mu_g.max(0.0) * GROUND_ALBEDO / PI);
+                    }
+                }
+            }
+            let count = (DIRECTIONS * DIRECTIONS) as f64;
+            let (second, transfer) = (second / count, transfer / count);
+            data.push(second.component_div(&(Rgb::repeat(1.0) - transfer)));
+        }
+    }
+    Table {
+        width: n,
+        height: n,
+        data,
+    }
+}
+
+/// Transmittance and multiple scattering tables for one `Medium`
+pub(crate) struct Luts {
+    pub medium: Medium,
+    pub transmittance: Texture,
+    pub multiscattering: Texture,
+}
+
+impl Luts {
+    pub fn new(medium: Medium) -> Self {
+        let transmittance = transmittance_table(&medium);
+        let multiscattering = multiscattering_table(&medium, &transmittance);
+        Self {
+            medium,
+            transmittance: transmittance.texture(),
+            multiscattering: multiscattering.texture(),
+        }
+    }
+}
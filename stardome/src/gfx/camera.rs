@@ -1,64 +1,197 @@
+// Animated move started by `Camera::fly_to`
+struct Flight {
+    from: (na::Vector3<f64>, na::UnitQuaternion<f32>),
+    to: (na::Vector3<f64>, na::UnitQuaternion<f32>),
+    duration: f32,
+    elapsed: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthMode {
+    /// OpenGL default, precision depends on the near/far ratio
+    Standard,
+    /// Reversed-Z with an infinite far plane.
+    ///
+    /// Needs clip control (GL 4.5 or ARB_clip_control), `Camera::set_depth` falls back to
+    /// `Logarithmic` without it.
+    ReversedZ,
+    /// Depth written by the shaders, works everywhere but disables early depth testing
+    Logarithmic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    /// Parallel projection, `height` is the visible height in world units.
+    ///
+    /// Logarithmic depth is not used, and reversed-Z keeps the far plane.
+    Orthographic {
+        height: f32,
+    },
+    /// Azimuthal equidistant fisheye covering `fov` degrees (180 for a dome master).
+    ///
+    /// Drawn to the faces of a cubemap by `dome_face` cameras, then warped onto the screen.
+    Fisheye {
+        fov: f32,
+    },
+}
+
+/// Drawables are rendered relative to the eye: model matrices are translated by the camera
+/// position in f64 before being cast to f32, and the view matrix is only a rotation
 pub struct Camera {
-    // TODO position using a coordinate system
-    // f64
-    pub position: na::Vector3<f32>,
-    pub rx: f32,
-    pub ry: f32,
-    pub rz: f32,
+    pub position: na::Vector3<f64>,
+    /// Rotation from camera to world space, the camera looks down -Z with +Y up
+    pub orientation: na::UnitQuaternion<f32>,
+    depth: DepthMode,
+    pub projection: Projection,
     fov: f32,
     near_plane: f32,
     far_plane: f32,
+    flight: Option<Flight>,
 }
 
 impl Camera {
-    pub fn new(position: na::Vector3<f32>, rx: f32, ry: f32, rz: f32) -> Self {
+    pub fn new(position: na::Vector3<f64>, rx: f32, ry: f32, rz: f32) -> Self {
         Self {
             position,
-            rx,
-            ry,
-            rz,
+            orientation: na::UnitQuaternion::from_euler_angles(rx, ry, rz),
+            depth: DepthMode::Standard,
+            projection: Projection::Perspective,
             fov: 90.0,
             near_plane: 1.0,
             far_plane: 500.0,
+            flight: None,
+        }
+    }
+
+    /// Orientation as rotations about x, then y, then z (radians)
+    pub fn angles(&self) -> (f32, f32, f32) {
+        self.orientation.euler_angles()
+    }
+
+    pub fn set_angles(&mut self, rx: f32, ry: f32, rz: f32) {
+        self.orientation = na::UnitQuaternion::from_euler_angles(rx, ry, rz);
+    }
+
+    /// Unit vector the camera looks along
+    pub fn forward(&self) -> na::Vector3<f32> {
+        self.orientation * -na::Vector3::z()
+    }
+
+    pub fn up(&self) -> na::Vector3<f32> {
+        self.orientation * na::Vector3::y()
+    }
+
+    /// Face `target`, with `up` as close to the top of the screen as possible
+    pub fn look_at(&mut self, target: &na::Vector3<f64>, up: &na::Vector3<f32>) {
+        self.orientation = look_at(&self.position, target, up, &self.orientation);
+    }
+
+    /// Turn along the shortest arc to face `direction`
+    pub fn point_towards(&mut self, direction: &na::Vector3<f32>) {
+        let r = na::UnitQuaternion::rotation_between(&self.forward(), direction)
+            // Directly behind
+            .unwrap_or_else(|| {
+                na::UnitQuaternion::from_axis_angle(
+                    &na::Unit::new_normalize(self.up()),
+                    std::f32::consts::PI,
+                )
+            });
+        self.orientation = r * self.orientation;
+    }
+
+    /// Move to `position` facing `target` over `duration` seconds, keeping roughly the same up.
+    ///
+    /// Advanced by `animate`, replacing any flight in progress.
+    pub fn fly_to(&mut self, position: na::Vector3<f64>, target: na::Vector3<f64>, duration: f32) {
+        self.flight = Some(Flight {
+            from: (self.position, self.orientation),
+            to: (
+                position,
+                look_at(&position, &target, &self.up(), &self.orientation),
+            ),
+            duration,
+            elapsed: 0.0,
+        });
+    }
+
+    pub fn is_flying(&self) -> bool {
+        self.flight.is_some()
+    }
+
+    /// Advance a flight by `dt` seconds, returns whether one is still in progress
+    pub fn animate(&mut self, dt: f32) -> bool {
+        let f = match &mut self.flight {
+            Some(f) => f,
+            None => return false,
+        };
+        f.elapsed += dt;
+        let t = if f.duration > 0.0 {
+            (f.elapsed / f.duration).min(1.0)
+        } else {
+            1.0
+        };
+        // Smoothstep, eases in and out
+        let s = t * t * (3.0 - 2.0 * t);
+        self.position = f.from.0.lerp(&f.to.0, s as f64);
+        self.orientation = f.from.1.slerp(&f.to.1, s);
+        if t >= 1.0 {
+            self.flight = None;
         }
+        self.flight.is_some()
     }
 
-    // [[1,0,0,0],[0,"cx",-"sx",0],[0,"sx","cx",0],[0,0,0,1]]*[["cy",0,"sy",0],[0,1,0,0],[-"sy",0,"cy",0],[0,0,0,1]]*[["cz",-"sz",0,0],["sz", "cz", 0, 0],[0,0,1,0],[0,0,0,1]]
     pub fn rot_matrix(&self) -> na::Matrix4<f32> {
-        let sx = (-self.rx).sin();
-        let cx = (-self.rx).cos();
-        let sy = (-self.ry).sin();
-        let cy = (-self.ry).cos();
-        let sz = (-self.rz).sin();
-        let cz = (-self.rz).cos();
-        na::Matrix4::new(
-            cy * cz,
-            -cy * sz,
-            sy,
-            0.0,
-            cx * sz + cz * sx * sy,
-            cx * cz - sx * sy * sz,
-            -cy * sx,
-            0.0,
-            sx * sz - cx * cz * sy,
-            cx * sy * sz + cz * sx,
-            cx * cy,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            1.0,
-        )
+        self.orientation.inverse().to_homogeneous()
     }
 
+    /// Rotation only, pair with `relative` model matrices
     pub fn view_matrix(&self) -> na::Matrix4<f32> {
-        self.rot_matrix().prepend_translation(&-self.position)
+        self.rot_matrix()
+    }
+
+    /// Model matrix moved so the camera is at the origin
+    pub fn relative(&self, model: &na::Matrix4<f64>) -> na::Matrix4<f32> {
+        na::convert(model.append_translation(&-self.position))
+    }
+
+    /// Point relative to the camera
+    pub fn relative_point(&self, p: &na::Vector3<f64>) -> na::Vector3<f32> {
+        na::convert(p - self.position)
     }
 
     // https://docs.rs/glam/0.11.2/glam/struct.Mat4.html#method.perspective_rh_gl
     pub fn projection_matrix(&self, aspect_ratio: f32) -> na::Matrix4<f32> {
+        let (f, aspect_ratio) = match self.projection {
+            Projection::Perspective => (1.0 / (self.fov.to_radians() / 2.0).tan(), aspect_ratio),
+            Projection::Orthographic { height } => {
+                return self.orthographic_matrix(height, aspect_ratio)
+            }
+            // A 90 degree square cubemap face
+            Projection::Fisheye { .. } => (1.0, 1.0),
+        };
+        if self.depth == DepthMode::ReversedZ {
+            // Depth is near / distance, 1 at the near plane and 0 at infinity
+            return na::Matrix4::new(
+                f / aspect_ratio,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                f,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                self.near_plane,
+                0.0,
+                0.0,
+                -1.0,
+                0.0,
+            );
+        }
         let inv_length = 1.0 / (self.near_plane - self.far_plane);
-        let f = 1.0 / (self.fov.to_radians() / 2.0).tan();
         let a = f / aspect_ratio;
         let b = (self.near_plane + self.far_plane) * inv_length;
         let c = (2.0 * self.near_plane * self.far_plane) * inv_length;
@@ -67,10 +200,57 @@ impl Camera {
         )
     }
 
+    fn orthographic_matrix(&self, height: f32, aspect_ratio: f32) -> na::Matrix4<f32> {
+        let (n, f) = (self.near_plane, self.far_plane);
+        let sx = 2.0 / (height * aspect_ratio);
+        let sy = 2.0 / height;
+        // Reversed-Z maps near to 1 and far to 0, otherwise near to -1 and far to 1
+        let (c, d) = if self.depth == DepthMode::ReversedZ {
+            (1.0 / (f - n), f / (f - n))
+        } else {
+            (-2.0 / (f - n), -(f + n) / (f - n))
+        };
+        na::Matrix4::new(
+            sx, 0.0, 0.0, 0.0, 0.0, sy, 0.0, 0.0, 0.0, 0.0, c, d, 0.0, 0.0, 0.0, 1.0,
+        )
+    }
+
     pub fn matrix(&self, aspect_ratio: f32) -> na::Matrix4<f32> {
         self.projection_matrix(aspect_ratio) * self.view_matrix()
     }
 
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self.projection, Projection::Orthographic { .. })
+    }
+
+    pub fn is_fisheye(&self) -> bool {
+        matches!(self.projection, Projection::Fisheye { .. })
+    }
+
+    pub fn depth(&self) -> DepthMode {
+        self.depth
+    }
+
+    /// Returns the mode actually used, `Logarithmic` in place of `ReversedZ` when `g` has no
+    /// clip control.
+    ///
+    /// The GL depth state follows in `Graphics::handle_frame`, at the end of the frame, so
+    /// set it between frames.
+    pub fn set_depth(&mut self, mode: DepthMode, g: &super::Graphics) -> DepthMode {
+        self.depth = if g.supports_depth(mode) {
+            mode
+        } else {
+            DepthMode::Logarithmic
+        };
+        self.depth
+    }
+
+    /// Whether the shaders write logarithmic depth, never in orthographic projections
+    pub fn is_log_depth(&self) -> bool {
+        // w is constant in orthographic projections
+        self.depth == DepthMode::Logarithmic && !self.is_orthographic()
+    }
+
     pub fn set_fov(&mut self, fov: f32) {
         if fov > 0.0 && fov < 180.0 {
             self.fov = fov;
@@ -94,4 +274,81 @@ impl Camera {
     pub fn get_clipping_planes(&self) -> (f32, f32) {
         (self.near_plane, self.far_plane)
     }
+
+    /// Camera for face `n` of a fisheye cubemap, in GL order (+X, -X, +Y, -Y, +Z, -Z)
+    pub fn dome_face(&self, n: u32) -> Camera {
+        Camera {
+            position: self.position,
+            orientation: self.orientation * super::dome::Dome::face_rotation(n),
+            depth: self.depth,
+            projection: self.projection,
+            fov: 90.0,
+            near_plane: self.near_plane,
+            far_plane: self.far_plane,
+            flight: None,
+        }
+    }
+
+    /// Normalized device coordinates of a point, `None` when it is not in view
+    pub fn project(&self, p: &na::Vector3<f64>, aspect_ratio: f32) -> Option<na::Vector2<f32>> {
+        let rel = self.relative_point(p);
+        if let Projection::Fisheye { fov } = self.projection {
+            // Inverse of dome.frag.glsl
+            let d = self.orientation.inverse() * rel;
+            // Angle from the view direction
+            let theta = d.xy().norm().atan2(-d.z);
+            let half_fov = fov.to_radians() / 2.0;
+            if theta > half_fov || d.norm() == 0.0 {
+                return None;
+            }
+            let phi = d.y.atan2(d.x);
+            let r = theta / half_fov;
+            let (x, y) = (r * phi.cos(), r * phi.sin());
+            return Some(if aspect_ratio > 1.0 {
+                na::Vector2::new(x / aspect_ratio, y)
+            } else {
+                na::Vector2::new(x, y * aspect_ratio)
+            });
+        }
+        let pce = self.matrix(aspect_ratio) * rel.push(1.0);
+        if pce.w <= 0.0 {
+            return None;
+        }
+        Some(pce.xy() / pce.w)
+    }
+
+    /// Scale for logarithmic depth in the shaders, 0 when it is off
+    pub fn log_depth(&self) -> f32 {
+        if self.is_log_depth() {
+            1.0 / (self.far_plane / self.near_plane + 1.0).log2()
+        } else {
+            0.0
+        }
+    }
+}
+
+// `current` is kept when there is no direction to face, and its forward or up stands in for
+// an `up` along the direction, where face_towards gives NaN
+fn look_at(
+    position: &na::Vector3<f64>,
+    target: &na::Vector3<f64>,
+    up: &na::Vector3<f32>,
+    current: &na::UnitQuaternion<f32>,
+) -> na::UnitQuaternion<f32> {
+    // face_towards points +Z along the direction, the camera looks down -Z
+    let dir: na::Vector3<f32> = na::convert(position - target);
+    if dir == na::Vector3::zeros() {
+        return *current;
+    }
+    let across = |u: &na::Vector3<f32>| u.cross(&dir).norm() > 1e-6 * u.norm() * dir.norm();
+    let forward = current * -na::Vector3::z();
+    let up = if across(up) {
+        *up
+    } else if across(&forward) {
+        forward
+    } else {
+        // Square to forward, so across when forward isn't
+        current * na::Vector3::y()
+    };
+    na::UnitQuaternion::face_towards(&dir, &up)
 }
@@ -0,0 +1,412 @@
+// Constellation stick figures, IAU boundaries and names, drawn at infinity with the stars
+use super::drawable::{Drawable, Pass, Points, Topology};
+use super::stars::Star;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::io::BufRead;
+
+const DEG: f64 = PI / 180.0;
+// Boundary edges are split into pieces this long (radians) so parallels of declination curve
+const BOUNDARY_STEP: f64 = DEG;
+// Line ends follow their stars' proper motion when the epoch moves this far (Julian years)
+const REPROPAGATE_YEARS: f64 = 0.1;
+
+/// Stick figure of one constellation
+#[derive(Debug, Clone)]
+pub struct Figure {
+    /// IAU abbreviation, such as `Ori`
+    pub abbr: String,
+    /// Ends of each line, in pairs
+    pub stars: Vec<Star>,
+}
+
+impl Figure {
+    /// Mean direction of the stars at Julian year `year`, where its name goes
+    pub fn center(&self, year: f64) -> na::Vector3<f64> {
+        self.stars
+            .iter()
+            .fold(na::Vector3::zeros(), |a, s| a + s.direction(year))
+            .normalize()
+    }
+}
+
+/// Parse a Stellarium `constellationship.fab` line: abbreviation, number of lines, then the HIP
+/// numbers of their ends.
+///
+/// Lines with a star missing from `stars` (by `Star::id`) are left out.
+pub fn parse_figure(line: &str, stars: &HashMap<u32, Star>) -> Option<Figure> {
+    let mut f = line.split_whitespace();
+    let abbr = f.next()?;
+    if abbr.starts_with('#') {
+        return None;
+    }
+    let n: usize = f.next()?.parse().ok()?;
+    let ids: Vec<u32> = f.take(2 * n).filter_map(|h| h.parse().ok()).collect();
+    let stars: Vec<Star> = ids
+        .chunks_exact(2)
+        .filter(|p| p.iter().all(|h| stars.contains_key(h)))
+        .flat_map(|p| p.iter().map(|h| stars[h]))
+        .collect();
+    if stars.is_empty() {
+        return None;
+    }
+    Some(Figure {
+        abbr: abbr.to_owned(),
+        stars,
+    })
+}
+
+/// Lines between the stars of each constellation
+pub struct ConstellationLines {
+    pub visible: bool,
+    pub color: u32, // RGBA
+    /// Pixels
+    pub width: f32,
+    figures: Vec<Figure>,
+    // Julian year the line ends were computed for
+    year: f64,
+    points: Points,
+}
+
+impl ConstellationLines {
+    /// Panics without any figures
+    pub fn new(figures: Vec<Figure>) -> Self {
+        let mut s = Self {
+            visible: true,
+            color: 0x4070C0A0,
+            width: 1.5,
+            points: Points::new(0xFFFFFFFF, 1.0, true, Self::ends(&figures, 2000.0)),
+            figures,
+            year: 2000.0,
+        };
+        s.points.set_topology(Topology::Segments);
+        s.points.at_infinity = true;
+        s
+    }
+
+    /// Stars are looked up by HIP number, so they should come from Hipparcos or Tycho-2
+    pub fn open<P: AsRef<std::path::Path>>(
+        path: P,
+        stars: &[Star],
+    ) -> Result<Self, crate::BoxError> {
+        Self::load(
+            &mut std::io::BufReader::new(std::fs::File::open(path)?),
+            stars,
+        )
+    }
+
+    /// One constellation per line, lines that can't be used are skipped
+    pub fn load<R: BufRead>(r: &mut R, stars: &[Star]) -> Result<Self, crate::BoxError> {
+        let by_id: HashMap<u32, Star> = stars
+            .iter()
+            .filter(|s| s.id != 0)
+            .map(|s| (s.id, *s))
+            .collect();
+        let mut figures = Vec::new();
+        for line in r.lines() {
+            if let Some(f) = parse_figure(&line?, &by_id) {
+                figures.push(f);
+            }
+        }
+        if figures.is_empty() {
+            return Err("no constellation lines with known stars".into());
+        }
+        Ok(Self::new(figures))
+    }
+
+    pub fn figures(&self) -> &[Figure] {
+        &self.figures
+    }
+
+    fn ends(figures: &[Figure], year: f64) -> Vec<na::Vector3<f32>> {
+        figures
+            .iter()
+            .flat_map(|f| f.stars.iter())
+            .map(|s| na::convert(s.direction(year)))
+            .collect()
+    }
+}
+
+impl Drawable for ConstellationLines {
+    fn draw(
+        &mut self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        pass: Pass,
+        s: na::Vector3<f32>,
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))] t: &mut Vec<(
+            na::Vector3<f64>,
+            u32,
+            imgui::ImString,
+        )>,
+    ) -> Result<(), std::ffi::NulError> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.points.color = self.color;
+        self.points.width = self.width;
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+        return self.points.draw(g, c, tf, pass, s, t);
+        #[cfg(any(target_os = "emscripten", feature = "gles"))]
+        self.points.draw(g, c, tf, pass, s)
+    }
+
+    fn update(&mut self, epoch: sputils::time::TDB) {
+        let year = 2000.0 + crate::clock::seconds_since_j2000(epoch) / (365.25 * 86400.0);
+        if (year - self.year).abs() > REPROPAGATE_YEARS {
+            self.year = year;
+            self.points.set_points(Self::ends(&self.figures, year));
+        }
+    }
+
+    fn passes(&self) -> &'static [Pass] {
+        &[Pass::Sky]
+    }
+}
+
+/// IAU constellation boundaries (Delporte 1930).
+///
+/// The boundaries run along hour circles and parallels of declination of the B1875 mean equator
+/// and equinox, they are precessed (IAU 2006) from there to ICRS so they stay put among the
+/// stars at any date.
+pub struct ConstellationBoundaries {
+    pub visible: bool,
+    pub color: u32, // RGBA
+    /// Pixels
+    pub width: f32,
+    pub style: super::drawable::LineStyle,
+    points: Points,
+}
+
+impl ConstellationBoundaries {
+    /// Corners of each constellation in order, as IAU abbreviation, right ascension and
+    /// declination (radians, B1875).
+    ///
+    /// Consecutive corners of the same constellation are joined, and its last to its first.
+    /// Panics without any corners.
+    pub fn new(corners: &[(String, f64, f64)]) -> Self {
+        let b1875 = sputils::time::TT::from_besselian(1875.0);
+        let to_icrs = sputils::coord::GCRS::gcrs_to_mean_mat(&b1875).transpose();
+        let direction = |ra: f64, dec: f64| -> na::Vector3<f32> {
+            let (sa, ca) = ra.sin_cos();
+            let (sd, cd) = dec.sin_cos();
+            na::convert(to_icrs * na::Vector3::new(cd * ca, cd * sa, sd))
+        };
+        // Neighbouring constellations share edges, draw them once
+        let key = |ra: f64, dec: f64| {
+            let ra = ra.rem_euclid(2.0 * PI);
+            ((ra * 1e6).round() as i64, (dec * 1e6).round() as i64)
+        };
+        let mut seen = HashSet::new();
+        let mut points = Vec::new();
+        let mut start = 0;
+        while start < corners.len() {
+            let abbr = &corners[start].0;
+            let end = corners[start..]
+                .iter()
+                .position(|c| &c.0 != abbr)
+                .map_or(corners.len(), |n| start + n);
+            let polygon = &corners[start..end];
+            for (i, a) in polygon.iter().enumerate() {
+                let b = &polygon[(i + 1) % polygon.len()];
+                let (ka, kb) = (key(a.1, a.2), key(b.1, b.2));
+                if ka == kb || !seen.insert(if ka < kb { (ka, kb) } else { (kb, ka) }) {
+                    continue;
+                }
+                // Straight in RA and Dec, the short way around
+                let d_ra = (b.1 - a.1 + PI).rem_euclid(2.0 * PI) - PI;
+                let d_dec = b.2 - a.2;
+                let length = d_dec.abs().max(d_ra.abs() * a.2.cos().max(b.2.cos()));
+                let n = (length / BOUNDARY_STEP).ceil().max(1.0) as usize;
+                for k in 0..n {
+                    for j in [k, k + 1].iter() {
+                        let f = *j as f64 / n as f64;
+                        points.push(direction(a.1 + d_ra * f, a.2 + d_dec * f));
+                    }
+                }
+            }
+            start = end;
+        }
+        let mut points = Points::new(0xFFFFFFFF, 1.0, true, points);
+        points.set_topology(Topology::Segments);
+        points.at_infinity = true;
+        Self {
+            visible: true,
+            color: 0x80604080,
+            width: 1.0,
+            // Radians along the sky
+            style: super::drawable::LineStyle::Dashed {
+                dash: 0.01,
+                gap: 0.005,
+            },
+            points,
+        }
+    }
+
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, crate::BoxError> {
+        Self::load(&mut std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    /// `bound_18.dat` from CDS (VI/49): right ascension (hours), declination (degrees) and
+    /// abbreviation per line, lines that can't be used are skipped
+    pub fn load<R: BufRead>(r: &mut R) -> Result<Self, crate::BoxError> {
+        let mut corners = Vec::new();
+        for line in r.lines() {
+            let line = line?;
+            let mut f = line.split_whitespace();
+            let ra = f.next().and_then(|x| x.parse::<f64>().ok());
+            let dec = f.next().and_then(|x| x.parse::<f64>().ok());
+            if let (Some(ra), Some(dec), Some(abbr)) = (ra, dec, f.next()) {
+                corners.push((abbr.to_uppercase(), ra * 15.0 * DEG, dec * DEG));
+            }
+        }
+        if corners.is_empty() {
+            return Err("no constellation boundaries".into());
+        }
+        Ok(Self::new(&corners))
+    }
+}
+
+impl Drawable for ConstellationBoundaries {
+    fn draw(
+        &mut self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        pass: Pass,
+        s: na::Vector3<f32>,
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))] t: &mut Vec<(
+            na::Vector3<f64>,
+            u32,
+            imgui::ImString,
+        )>,
+    ) -> Result<(), std::ffi::NulError> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.points.color = self.color;
+        self.points.width = self.width;
+        self.points.style = self.style;
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+        return self.points.draw(g, c, tf, pass, s, t);
+        #[cfg(any(target_os = "emscripten", feature = "gles"))]
+        self.points.draw(g, c, tf, pass, s)
+    }
+
+    fn passes(&self) -> &'static [Pass] {
+        &[Pass::Sky]
+    }
+}
+
+struct Label {
+    abbr: String,
+    name: String,
+    direction: na::Vector3<f64>,
+}
+
+/// Constellation names on the sky, labelled with their abbreviations until `load_names`
+pub struct ConstellationNames {
+    pub visible: bool,
+    pub color: u32, // RGBA
+    labels: Vec<Label>,
+}
+
+impl ConstellationNames {
+    /// IAU abbreviation and ICRS direction of each label
+    pub fn new(labels: Vec<(String, na::Vector3<f64>)>) -> Self {
+        Self {
+            visible: true,
+            color: 0x6090E0FF,
+            labels: labels
+                .into_iter()
+                .map(|(abbr, d)| Label {
+                    name: abbr.clone(),
+                    abbr,
+                    direction: d.normalize(),
+                })
+                .collect(),
+        }
+    }
+
+    /// A label at the middle of each stick figure
+    pub fn from_lines(lines: &ConstellationLines) -> Self {
+        Self::new(
+            lines
+                .figures()
+                .iter()
+                .map(|f| (f.abbr.clone(), f.center(2000.0)))
+                .collect(),
+        )
+    }
+
+    pub fn open_names<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<(), crate::BoxError> {
+        self.load_names(&mut std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    /// Stellarium `constellation_names.eng.fab`: abbreviation then the name in quotes per line.
+    ///
+    /// Constellations missing from it keep their abbreviations.
+    pub fn load_names<R: BufRead>(&mut self, r: &mut R) -> Result<(), crate::BoxError> {
+        let mut names = HashMap::new();
+        for line in r.lines() {
+            let line = line?;
+            let abbr = match line.split_whitespace().next() {
+                Some(a) if !a.starts_with('#') => a.to_uppercase(),
+                _ => continue,
+            };
+            if let Some(name) = line.split('"').nth(1) {
+                names.insert(abbr, name.to_owned());
+            }
+        }
+        for l in self.labels.iter_mut() {
+            if let Some(name) = names.get(&l.abbr.to_uppercase()) {
+                l.name = name.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drawable for ConstellationNames {
+    #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+    fn draw(
+        &mut self,
+        _g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        _pass: Pass,
+        _s: na::Vector3<f32>,
+        t: &mut Vec<(na::Vector3<f64>, u32, imgui::ImString)>,
+    ) -> Result<(), std::ffi::NulError> {
+        // No sky in diagrams
+        if !self.visible || c.is_orthographic() {
+            return Ok(());
+        }
+        for l in self.labels.iter() {
+            // Far enough that moving the camera doesn't move the label
+            let p = c.position + tf.transform_vector(&l.direction) * 1e9;
+            t.push((p, self.color, imgui::ImString::new(l.name.clone())));
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_os = "emscripten", feature = "gles"))]
+    fn draw(
+        &mut self,
+        _g: &mut super::Graphics,
+        _c: &super::camera::Camera,
+        _tf: &na::Matrix4<f64>,
+        _pass: Pass,
+        _s: na::Vector3<f32>,
+    ) -> Result<(), std::ffi::NulError> {
+        Ok(())
+    }
+
+    fn passes(&self) -> &'static [Pass] {
+        &[Pass::Labels]
+    }
+}
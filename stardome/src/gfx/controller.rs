@@ -0,0 +1,235 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+
+// Keep the orbit away from the poles, where the up vector is undefined
+const MAX_PITCH: f64 = 89.9 * std::f64::consts::PI / 180.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Orbit around the target, always facing it
+    Orbit,
+    /// Six degrees of freedom
+    FreeFly,
+    /// Orbit in a frame travelling with the target, so the camera stays behind it
+    Follow,
+}
+
+// Motion still to be applied, eased out over the damping time
+#[derive(Default)]
+struct Pending {
+    yaw: f64,
+    pitch: f64,
+    roll: f64,
+    // Natural log of the distance (orbit) or move speed (free-fly)
+    zoom: f64,
+    // Camera-local translation (free-fly)
+    translation: na::Vector3<f64>,
+}
+
+#[derive(Default)]
+struct Keys {
+    w: bool,
+    a: bool,
+    s: bool,
+    d: bool,
+    q: bool,
+    e: bool,
+}
+
+/// Mouse and keyboard camera control.
+///
+/// Left drag rotates, the wheel zooms (or changes speed when flying).
+/// In orbit/follow modes W/S and A/D orbit and Q/E zoom,
+/// when flying W/S move, A/D strafe and Q/E roll.
+/// The pose is read back from the camera every update, so it can still be set directly.
+pub struct CameraController {
+    pub mode: CameraMode,
+    /// Point orbited and followed
+    pub target: na::Vector3<f64>,
+    /// Radians per pixel of mouse drag
+    pub mouse_sensitivity: f64,
+    /// Change in the log of distance per wheel step
+    pub zoom_sensitivity: f64,
+    /// Radians per second while a key is held
+    pub key_sensitivity: f64,
+    /// Free-fly speed (units per second)
+    pub move_speed: f64,
+    /// Closest approach to the target
+    pub min_distance: f64,
+    /// Time constant for easing out motion (seconds), 0 for none
+    pub damping: f64,
+    pending: Pending,
+    keys: Keys,
+    // Target and its frame at the last update, for follow mode
+    last_target: na::Vector3<f64>,
+    last_frame: Option<na::Rotation3<f64>>,
+}
+
+impl CameraController {
+    pub fn new(mode: CameraMode, target: na::Vector3<f64>) -> Self {
+        Self {
+            mode,
+            target,
+            mouse_sensitivity: 0.005,
+            zoom_sensitivity: 0.1,
+            key_sensitivity: 1.0,
+            move_speed: 1.0,
+            min_distance: 0.01,
+            damping: 0.1,
+            pending: Pending::default(),
+            keys: Keys::default(),
+            last_target: target,
+            last_frame: None,
+        }
+    }
+
+    /// Change the target without moving the camera
+    pub fn set_target(&mut self, target: na::Vector3<f64>) {
+        self.target = target;
+        self.last_target = target;
+    }
+
+    /// Move the target and the camera with it, used every frame to follow something
+    pub fn follow(&mut self, target: na::Vector3<f64>) {
+        self.target = target;
+    }
+
+    pub fn follow_planet(&mut self, p: &super::drawable::Planet) {
+        self.follow(p.position());
+    }
+
+    /// Returns whether the event was used
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseMotion {
+                mousestate,
+                xrel,
+                yrel,
+                ..
+            } if mousestate.left() => {
+                self.pending.yaw -= *xrel as f64 * self.mouse_sensitivity;
+                self.pending.pitch -= *yrel as f64 * self.mouse_sensitivity;
+                true
+            }
+            Event::MouseWheel { y, .. } => {
+                self.pending.zoom -= *y as f64 * self.zoom_sensitivity;
+                true
+            }
+            Event::KeyDown {
+                scancode: Some(sc), ..
+            } => self.set_key(*sc, true),
+            Event::KeyUp {
+                scancode: Some(sc), ..
+            } => self.set_key(*sc, false),
+            _ => false,
+        }
+    }
+
+    fn set_key(&mut self, sc: Scancode, down: bool) -> bool {
+        let k = match sc {
+            Scancode::W => &mut self.keys.w,
+            Scancode::A => &mut self.keys.a,
+            Scancode::S => &mut self.keys.s,
+            Scancode::D => &mut self.keys.d,
+            Scancode::Q => &mut self.keys.q,
+            Scancode::E => &mut self.keys.e,
+            _ => return false,
+        };
+        *k = down;
+        true
+    }
+
+    /// Apply input gathered since the last update, `dt` in seconds
+    pub fn update(&mut self, cam: &mut super::camera::Camera, dt: f64) {
+        let axis = |pos: bool, neg: bool| (pos as i8 - neg as i8) as f64;
+        let ws = axis(self.keys.w, self.keys.s);
+        let da = axis(self.keys.d, self.keys.a);
+        let eq = axis(self.keys.e, self.keys.q);
+        let turn = self.key_sensitivity * dt;
+        match self.mode {
+            CameraMode::FreeFly => {
+                self.pending.translation += na::Vector3::new(da, 0.0, -ws) * self.move_speed * dt;
+                self.pending.roll -= eq * turn;
+            }
+            CameraMode::Orbit | CameraMode::Follow => {
+                self.pending.yaw -= da * turn;
+                self.pending.pitch += ws * turn;
+                self.pending.zoom += eq * turn;
+            }
+        }
+
+        // Fraction of the pending motion to apply this frame
+        let k = if self.damping > 0.0 {
+            1.0 - (-dt / self.damping).exp()
+        } else {
+            1.0
+        };
+        let yaw = self.pending.yaw * k;
+        let pitch = self.pending.pitch * k;
+        let roll = self.pending.roll * k;
+        let zoom = self.pending.zoom * k;
+        let translation = self.pending.translation * k;
+        self.pending.yaw -= yaw;
+        self.pending.pitch -= pitch;
+        self.pending.roll -= roll;
+        self.pending.zoom -= zoom;
+        self.pending.translation -= translation;
+
+        let position = cam.position;
+        match self.mode {
+            CameraMode::FreeFly => {
+                let q: na::UnitQuaternion<f64> = na::convert(cam.orientation);
+                let q = q * na::UnitQuaternion::from_euler_angles(pitch, yaw, roll);
+                cam.position = position + q * translation;
+                cam.orientation = na::convert(q);
+                self.move_speed *= (-zoom).exp();
+                self.last_frame = None;
+            }
+            CameraMode::Orbit | CameraMode::Follow => {
+                let (last, frame) = if self.mode == CameraMode::Follow {
+                    let frame = self.follow_frame();
+                    (self.last_frame.unwrap_or(frame), frame)
+                } else {
+                    (na::Rotation3::identity(), na::Rotation3::identity())
+                };
+                // Offset from the target in its previous frame
+                let offset = last.inverse() * (position - self.last_target);
+                let distance = offset.norm().max(self.min_distance);
+                let mut lon = offset.y.atan2(offset.x);
+                let mut lat = (offset.z / distance).asin();
+                lon += yaw;
+                lat = (lat + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+                let distance = (distance * zoom.exp()).max(self.min_distance);
+                // Distance has no effect on the size of things in an orthographic view
+                if let super::camera::Projection::Orthographic { height } = &mut cam.projection {
+                    *height *= zoom.exp() as f32;
+                }
+                let offset = frame
+                    * na::Vector3::new(lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin())
+                    * distance;
+                cam.position = self.target + offset;
+                // Camera looks down -Z
+                let q = na::UnitQuaternion::face_towards(&offset, &(frame * na::Vector3::z()));
+                cam.orientation = na::convert(q);
+                self.last_frame = if self.mode == CameraMode::Follow {
+                    Some(frame)
+                } else {
+                    None
+                };
+            }
+        }
+        self.last_target = self.target;
+    }
+
+    // x along the target's motion, z as close to the world's z as possible
+    fn follow_frame(&self) -> na::Rotation3<f64> {
+        let v = self.target - self.last_target;
+        let z = na::Vector3::z();
+        let x = v - z * z.dot(&v);
+        if x.norm() < f64::EPSILON * self.target.norm().max(1.0) {
+            return self.last_frame.unwrap_or_else(na::Rotation3::identity);
+        }
+        let x = x.normalize();
+        na::Rotation3::from_basis_unchecked(&[x, z.cross(&x), z])
+    }
+}
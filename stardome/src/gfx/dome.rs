@@ -0,0 +1,177 @@
+// Cubemap render target for fisheye projections
+// Every drawable is rendered once per face in view, then warped onto the screen by the dome shader
+
+/// Edge length of each face in pixels
+pub const FACE_SIZE: i32 = 1024;
+
+pub struct Dome {
+    fbo: u32,
+    color: u32,
+    // Depth has to be per face, as drawables are drawn to all faces one after another
+    depth: u32,
+    // Empty, the fullscreen triangle is generated in the vertex shader
+    vao: u32,
+}
+
+impl Dome {
+    pub fn new() -> Self {
+        let mut fbo = 0;
+        let mut color = 0;
+        let mut depth = 0;
+        let mut vao = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::GenTextures(1, &mut color);
+            gl::GenTextures(1, &mut depth);
+            gl::GenVertexArrays(1, &mut vao);
+
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, color);
+            for n in 0..6 {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + n,
+                    0,
+                    gl::RGBA8 as i32,
+                    FACE_SIZE,
+                    FACE_SIZE,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    std::ptr::null(),
+                );
+            }
+            cube_parameters();
+
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, depth);
+            for n in 0..6 {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + n,
+                    0,
+                    gl::DEPTH_COMPONENT24 as i32,
+                    FACE_SIZE,
+                    FACE_SIZE,
+                    0,
+                    gl::DEPTH_COMPONENT,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null(),
+                );
+            }
+            cube_parameters();
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        Self {
+            fbo,
+            color,
+            depth,
+            vao,
+        }
+    }
+
+    /// Render into face `n`, in GL order (+X, -X, +Y, -Y, +Z, -Z)
+    pub fn bind_face(&self, n: u32) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + n,
+                self.color,
+                0,
+            );
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + n,
+                self.depth,
+                0,
+            );
+            gl::Viewport(0, 0, FACE_SIZE, FACE_SIZE);
+        }
+    }
+
+    /// Back to the window, `size` in pixels
+    pub fn unbind(&self, size: (u32, u32)) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, size.0 as i32, size.1 as i32);
+        }
+    }
+
+    pub fn clear(&self) {
+        for n in 0..6 {
+            self.bind_face(n);
+            unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT) }
+        }
+    }
+
+    /// Rotation from a face's camera to the main camera
+    pub fn face_rotation(n: u32) -> na::UnitQuaternion<f32> {
+        // Same up vectors as a cubemap lookup, so faces can be sampled by direction
+        let (dir, up) = match n {
+            0 => (na::Vector3::x(), -na::Vector3::y()),
+            1 => (-na::Vector3::x(), -na::Vector3::y()),
+            2 => (na::Vector3::y(), na::Vector3::z()),
+            3 => (-na::Vector3::y(), -na::Vector3::z()),
+            4 => (na::Vector3::z(), -na::Vector3::y()),
+            _ => (-na::Vector3::z(), -na::Vector3::y()),
+        };
+        na::UnitQuaternion::face_towards(&-dir, &up)
+    }
+
+    /// Whether any of face `n` is within a fisheye of `fov` degrees
+    pub fn face_visible(n: u32, fov: f32) -> bool {
+        use std::f32::consts::{FRAC_PI_4, PI};
+        // Angle from the view direction to the nearest point of the face, an edge of the
+        // sides and a corner of the back
+        let nearest = match n {
+            0..=3 => FRAC_PI_4,
+            4 => PI - (1.0 / 3.0_f32.sqrt()).acos(),
+            _ => 0.0,
+        };
+        fov.to_radians() / 2.0 > nearest
+    }
+
+    /// Warp the faces onto the bound framebuffer with an azimuthal equidistant projection
+    pub fn draw(&self, prog: &super::shader::Program, fov: f32, aspect_ratio: f32) {
+        prog.use_gl();
+        let _ = prog.set_float("half_fov", fov.to_radians() / 2.0);
+        let _ = prog.set_float("aspect", aspect_ratio);
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.color);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            gl::BindVertexArray(0);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        prog.unuse_gl();
+    }
+}
+
+impl Drop for Dome {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.color);
+            gl::DeleteTextures(1, &self.depth);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+unsafe fn cube_parameters() {
+    for &p in &[gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, p, gl::CLAMP_TO_EDGE as i32);
+    }
+    gl::TexParameteri(
+        gl::TEXTURE_CUBE_MAP,
+        gl::TEXTURE_MIN_FILTER,
+        gl::LINEAR as i32,
+    );
+    gl::TexParameteri(
+        gl::TEXTURE_CUBE_MAP,
+        gl::TEXTURE_MAG_FILTER,
+        gl::LINEAR as i32,
+    );
+}
@@ -2,19 +2,71 @@
 // Passed by mutable reference to program
 // Then drawn
 
+/// Stages of a frame, in the order they are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Pass {
+    /// Writes depth, drawn first (followed by the skybox)
+    Opaque,
+    /// At infinity over the skybox, hidden by opaque geometry, see `Graphics::begin_sky`
+    Sky,
+    /// Additive, over opaque geometry
+    Atmosphere,
+    /// Blended, sorted back to front
+    Transparent,
+    /// Text collected for the overlay, drawn just before the UI
+    Labels,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 5] = [
+        Pass::Opaque,
+        Pass::Sky,
+        Pass::Atmosphere,
+        Pass::Transparent,
+        Pass::Labels,
+    ];
+}
+
 pub trait Drawable {
     // Perhaps some type equals error kind of thing
     // The jank is real
+    /// Called once for each of `passes`.
+    ///
+    /// `tf` is the world transform of the scene node drawn in, identity outside a scene
     fn draw(
         &mut self,
         g: &mut super::Graphics,
         c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        pass: Pass,
         s: na::Vector3<f32>,
         #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))] t: &mut Vec<(na::Vector3<f64>, u32, imgui::ImString)>,
     ) -> Result<(), std::ffi::NulError>;
+
+    /// Called once per frame before drawing, with the simulation epoch
+    fn update(&mut self, _epoch: sputils::time::TDB) {}
+
+    /// Called once per frame after `update`, with the main camera rather than a dome face's
+    /// and `tf` as in `draw`
+    fn update_view(
+        &mut self,
+        _g: &mut super::Graphics,
+        _c: &super::camera::Camera,
+        _tf: &na::Matrix4<f64>,
+    ) {
+    }
+
+    /// Passes to be drawn in
+    fn passes(&self) -> &'static [Pass] {
+        &[Pass::Opaque]
+    }
+
+    /// Point used to sort transparent drawables, in the frame of their node
+    fn origin(&self) -> na::Vector3<f64> {
+        na::Vector3::zeros()
+    }
 }
 
-// Future improvement: displacement/normal map
 pub struct Planet {
     /// Equatorial radius
     pub r_equatorial: f64,
@@ -22,12 +74,59 @@ pub struct Planet {
     pub r_polar: f64,
     pub lighting: bool,
     pub texture: super::texture::Texture,
+    /// Emissive texture blended in on the night side, such as city lights
+    pub night: Option<super::texture::Texture>,
+    /// Brightness of `night`
+    pub night_intensity: f32,
+    /// Tangent space normals (east, north, up in RGB), mapped like the texture
+    pub normal_map: Option<super::texture::Texture>,
+    /// Specular strength in red and roughness in green, instead of the water in the
+    /// texture's alpha
+    pub specular_map: Option<super::texture::Texture>,
+    pub relief: Option<Relief>,
     pub atm: Option<Atmosphere>,
     pub clouds: Option<Clouds>,
-    /// Transformation matrix, rotation and translation only (no scaling)
+    /// Streamed cube-sphere surface, drawn instead of `texture` and the maps, see
+    /// `Planet::with_terrain`
+    pub terrain: Option<super::terrain::Terrain>,
+    /// Transformation matrix, rotation and translation only (no scaling).
+    ///
+    /// Relative to the scene node it is attached to, if any
     pub tf: na::Matrix4<f64>,
+    /// Sets `tf` from the simulation epoch every frame
+    pub source: Option<Box<dyn crate::BodySource>>,
+    // Atmosphere lookup tables, made in `update` for the parameters of `atm`
+    luts: Option<super::atmosphere::Luts>,
+}
+
+/// Height map displacing a planet's surface, also shading it when there is no normal map
+pub struct Relief {
+    /// Height in the red channel, mapped like the planet's texture
+    pub texture: super::texture::Texture,
+    /// Heights (units, from the ellipsoid) of black and white
+    pub min: f64,
+    pub max: f64,
+    /// Multiplies the heights, relief is hard to see at true scale
+    pub exaggeration: f64,
+}
+
+impl Relief {
+    /// Heights in km
+    pub fn new(texture: super::texture::Texture, min: f64, max: f64) -> Self {
+        Self {
+            texture,
+            min: min / crate::KM_PER_UNIT,
+            max: max / crate::KM_PER_UNIT,
+            exaggeration: 1.0,
+        }
+    }
 }
 
+// Depression of the Sun (degrees) where the night texture is fully on, the end of nautical
+// twilight with an atmosphere and just past the terminator without
+const NIGHT_DEPRESSION_ATM: f32 = 12.0;
+const NIGHT_DEPRESSION: f32 = 2.0;
+
 // Need more atmosphere parameters
 // Make sure to deal with different positions orientations scales properly
 // TODO Use f32s/f64s consistently
@@ -43,17 +142,137 @@ pub struct Atmosphere {
     pub asymmetry_m: f64,
 }
 
-// Should include cloud rotation
-// Can be done by keeping track of elapsed time
-// Shouldn't need a "state" per se
+impl Atmosphere {
+    // What the lookup tables depend on, radii in metres
+    fn medium(&self, r_ground: f64, r_top: f64) -> super::atmosphere::Medium {
+        super::atmosphere::Medium {
+            r_ground,
+            r_top,
+            scale_height_r: self.scale_height_r,
+            scatter_r: na::convert(self.scatter_coeff_r),
+            scale_height_m: self.scale_height_m,
+            scatter_m: na::convert(self.scatter_coeff_m),
+        }
+    }
+}
+
+impl From<&sputils::bodies::Atmosphere> for Atmosphere {
+    fn from(a: &sputils::bodies::Atmosphere) -> Self {
+        let [r, g, b] = a.rayleigh_scattering;
+        Self {
+            offset: a.height / crate::KM_PER_UNIT,
+            // Not physical, tuned by eye
+            sun_intensity: 20.0,
+            scale_height_r: a.rayleigh_scale_height,
+            scatter_coeff_r: na::Vector3::new(r as f32, g as f32, b as f32),
+            scale_height_m: a.mie_scale_height,
+            scatter_coeff_m: na::Vector3::from_element(a.mie_scattering as f32),
+            asymmetry_m: a.mie_asymmetry,
+        }
+    }
+}
+
+/// Shell of cloud over a planet, lit like the ground and shadowing it
 pub struct Clouds {
     // Offset from point on ground
     // This way it fits with oblate spheroid
     pub offset: f64,
+    /// Cover in the red channel, mapped like the planet's texture
     pub texture: super::texture::Texture,
+    /// Eastward drift over the ground (radians per second)
+    pub rotation_rate: f64,
+    /// Darkening of the ground under full cover, 0 to 1
+    pub shadow: f32,
+    // Drift at the last update, from the epoch alone so there is no other state
+    angle: f64,
+}
+
+impl Clouds {
+    /// 10 km up, drifting east a degree a day
+    pub fn new(texture: super::texture::Texture) -> Self {
+        Self {
+            offset: 10.0 / crate::KM_PER_UNIT,
+            texture,
+            rotation_rate: 1.0_f64.to_radians() / 86400.0,
+            shadow: 0.5,
+            angle: 0.0,
+        }
+    }
+
+    fn rotation(&self) -> na::Matrix4<f64> {
+        na::Matrix4::from_axis_angle(&na::Vector3::z_axis(), self.angle)
+    }
 }
 
 impl Planet {
+    /// Size, shape and atmosphere from a body's physical constants, with identity transform
+    pub fn new(c: &sputils::bodies::Constants, texture: super::texture::Texture) -> Self {
+        Self {
+            r_equatorial: c.r_equatorial / crate::KM_PER_UNIT,
+            r_polar: c.r_polar / crate::KM_PER_UNIT,
+            lighting: true,
+            texture,
+            night: None,
+            night_intensity: 1.0,
+            normal_map: None,
+            specular_map: None,
+            relief: None,
+            atm: c.atmosphere.as_ref().map(Atmosphere::from),
+            clouds: None,
+            terrain: None,
+            tf: na::Matrix4::identity(),
+            source: None,
+            luts: None,
+        }
+    }
+
+    /// Move with `source` from now on, eg an `EphemerisSource` or a closure of the epoch
+    pub fn with_source<S: crate::BodySource + 'static>(mut self, source: S) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// Show `texture` on the night side, eg NASA's Black Marble
+    pub fn with_night(mut self, texture: super::texture::Texture) -> Self {
+        self.night = Some(texture);
+        self
+    }
+
+    /// Cover with `clouds` from now on
+    pub fn with_clouds(mut self, clouds: Clouds) -> Self {
+        self.clouds = Some(clouds);
+        self
+    }
+
+    /// Draw the surface from tiles, with heights from `elevation` if any
+    ///
+    /// The tiles have their own texture coordinates, so night lights, cloud shadows,
+    /// `normal_map`, `specular_map` and `relief` are disabled while terrain is drawn. The
+    /// clouds themselves are still drawn above it.
+    pub fn with_terrain(
+        mut self,
+        imagery: super::terrain::TileSource,
+        elevation: Option<super::terrain::Elevation>,
+    ) -> Self {
+        self.terrain = Some(super::terrain::Terrain::new(
+            imagery,
+            elevation,
+            self.r_equatorial,
+            self.r_polar,
+        ));
+        self
+    }
+
+    /// `None` for barycentres
+    pub fn from_body(b: sputils::eph::Body, texture: super::texture::Texture) -> Option<Self> {
+        sputils::bodies::body(b).map(|c| Self::new(c, texture))
+    }
+
+    /// Centre, from the transform
+    pub fn position(&self) -> na::Vector3<f64> {
+        na::Vector3::new(self.tf.m14, self.tf.m24, self.tf.m34)
+    }
+
     pub fn mat64(&self, atm: Option<&Atmosphere>) -> na::Matrix4<f64> {
         if let Some(a) = atm {
             return self.tf
@@ -74,6 +293,124 @@ impl Planet {
     pub fn mat32(&self, atm: Option<&Atmosphere>) -> na::Matrix4<f32> {
         na::convert(self.mat64(atm))
     }
+
+    /// Model matrix relative to the camera
+    pub fn mat_relative(
+        &self,
+        c: &super::camera::Camera,
+        atm: Option<&Atmosphere>,
+    ) -> na::Matrix4<f32> {
+        c.relative(&self.mat64(atm))
+    }
+
+    fn draw_clouds(
+        &self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        s: na::Vector3<f32>,
+        view: &na::Matrix4<f32>,
+        projection: &na::Matrix4<f32>,
+    ) -> Result<(), std::ffi::NulError> {
+        let cl = match &self.clouds {
+            Some(cl) => cl,
+            None => return Ok(()),
+        };
+        let z = na::Vector3::zeros();
+        let model = tf
+            * self.tf
+            * cl.rotation()
+            * na::Matrix4::new_nonuniform_scaling(&na::Vector3::new(
+                self.r_equatorial + cl.offset,
+                self.r_equatorial + cl.offset,
+                self.r_polar + cl.offset,
+            ));
+        // Seen from below, the inside of the shell faces the camera
+        let centre = (tf * self.position().push(1.0)).xyz();
+        let inside = (c.position - centre).norm() < self.r_equatorial + cl.offset;
+        g.progs[9].use_gl();
+        g.progs[9].set_mat4("model", &c.relative(&model))?;
+        g.progs[9].set_mat4("view", view)?;
+        g.progs[9].set_mat4("projection", projection)?;
+        g.progs[9].set_depth(c)?;
+        g.progs[9].set_vec3("sun", if self.lighting { &s } else { &z })?;
+        cl.texture.bind(0);
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            if inside {
+                gl::CullFace(gl::FRONT);
+            }
+        }
+        g.meshes[0].draw();
+        unsafe {
+            gl::CullFace(gl::BACK);
+            gl::Disable(gl::BLEND);
+            gl::DepthMask(gl::TRUE);
+        }
+        g.progs[9].unuse_gl();
+        Ok(())
+    }
+
+    fn draw_atmosphere(
+        &self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        s: na::Vector3<f32>,
+        view: &na::Matrix4<f32>,
+        projection: &na::Matrix4<f32>,
+    ) -> Result<(), std::ffi::NulError> {
+        let z = na::Vector3::zeros();
+        if !self.lighting || s == z {
+            return Ok(());
+        }
+        let a = match &self.atm {
+            Some(a) => a,
+            None => return Ok(()),
+        };
+        // Seen from below, the inside of the shell faces the camera
+        let centre = (tf * self.position().push(1.0)).xyz();
+        let inside = (c.position - centre).norm() < self.r_equatorial + a.offset;
+        g.progs[3].use_gl();
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::BLEND);
+            // Light scattered in along the ray, over what is behind dimmed by the alpha
+            gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+            if inside {
+                gl::CullFace(gl::FRONT);
+            }
+        }
+        g.progs[3].set_mat4("model", &c.relative(&(tf * self.mat64(Some(a)))))?;
+        g.progs[3].set_mat4("view", view)?;
+        g.progs[3].set_mat4("projection", projection)?;
+        g.progs[3].set_depth(c)?;
+        g.progs[3].set_vec3("sun_dir", &s)?;
+        g.progs[3].set_vec3("cam_pos", &z)?;
+        g.progs[3].set_vec3("pos", &na::convert((c.position - centre) * 1e6))?;
+        g.progs[3].set_float("Re", (self.r_equatorial * 1e6) as f32)?;
+        g.progs[3].set_float("Ra", ((self.r_equatorial + a.offset) * 1e6) as f32)?;
+        g.progs[3].set_float("Hr", a.scale_height_r as f32)?;
+        g.progs[3].set_vec3("betaR", &a.scatter_coeff_r)?;
+        g.progs[3].set_float("Hm", a.scale_height_m as f32)?;
+        g.progs[3].set_vec3("betaM", &a.scatter_coeff_m)?;
+        g.progs[3].set_float("g", a.asymmetry_m as f32)?;
+        g.progs[3].set_float("intensity", a.sun_intensity as f32)?;
+        if let Some(l) = &self.luts {
+            l.transmittance.bind(0);
+            l.multiscattering.bind(1);
+        }
+        g.meshes[0].draw();
+        unsafe {
+            gl::CullFace(gl::BACK);
+            gl::Disable(gl::BLEND);
+            gl::DepthMask(gl::TRUE);
+        }
+        g.progs[3].unuse_gl();
+        Ok(())
+    }
 }
 
 impl Drawable for Planet {
@@ -81,6 +418,8 @@ impl Drawable for Planet {
         &mut self,
         g: &mut super::Graphics,
         c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        pass: Pass,
         s: na::Vector3<f32>,
         #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))] _t: &mut Vec<(
             na::Vector3<f64>,
@@ -90,53 +429,182 @@ impl Drawable for Planet {
     ) -> Result<(), std::ffi::NulError> {
         let view = c.view_matrix();
         let projection = c.projection_matrix(g.aspect_ratio());
+        let z = na::Vector3::zeros();
+        if pass == Pass::Atmosphere {
+            // Under the atmosphere, over everything opaque
+            self.draw_clouds(g, c, tf, s, &view, &projection)?;
+            return self.draw_atmosphere(g, c, tf, s, &view, &projection);
+        }
         g.progs[0].use_gl();
         println!("{:?}", g.progs);
-        g.progs[0].set_mat4("model", &self.mat32(None))?;
+        g.progs[0].set_mat4("model", &c.relative(&(tf * self.mat64(None))))?;
         println!("{:?}", g.progs);
         g.progs[0].set_mat4("view", &view)?;
         g.progs[0].set_mat4("projection", &projection)?;
-        let z = na::Vector3::zeros();
+        g.progs[0].set_depth(c)?;
         g.progs[0].set_vec3("sun", if self.lighting { &s } else { &z })?;
-        g.progs[0].set_vec3("cam_pos", &c.position)?;
-
-        self.texture.bind(0);
-        g.meshes[0].draw();
-        g.progs[0].unuse_gl();
+        // World space is centred on the camera
+        g.progs[0].set_vec3("cam_pos", &z)?;
+        let depression = if self.atm.is_some() {
+            NIGHT_DEPRESSION_ATM
+        } else {
+            NIGHT_DEPRESSION
+        };
+        g.progs[0].set_float("twilight", depression.to_radians().sin())?;
+        match &self.night {
+            Some(n) if self.lighting => {
+                n.bind(1);
+                g.progs[0].set_float("night_intensity", self.night_intensity)?;
+            }
+            _ => g.progs[0].set_float("night_intensity", 0.0)?,
+        }
+        match &self.clouds {
+            Some(cl) if self.lighting => {
+                cl.texture.bind(2);
+                let m = tf * self.tf;
+                let r: na::Matrix3<f32> = na::convert(na::Matrix3::from_fn(|i, j| m[(i, j)]));
+                g.progs[0].set_vec3("sun_model", &(r.transpose() * s))?;
+                g.progs[0].set_float("cloud_shadow", cl.shadow)?;
+                g.progs[0].set_float("cloud_angle", cl.angle as f32)?;
+                g.progs[0].set_float(
+                    "cloud_radius",
+                    (1.0 + cl.offset / self.r_equatorial) as f32,
+                )?;
+            }
+            _ => g.progs[0].set_float("cloud_shadow", 0.0)?,
+        }
+        if let Some(n) = &self.normal_map {
+            n.bind(3);
+        }
+        g.progs[0].set_int("has_normal_map", self.normal_map.is_some() as i32)?;
+        if let Some(m) = &self.specular_map {
+            m.bind(4);
+        }
+        g.progs[0].set_int("has_specular_map", self.specular_map.is_some() as i32)?;
+        let height_range = match &self.relief {
+            Some(r) => {
+                r.texture.bind(5);
+                let k = r.exaggeration / self.r_equatorial;
+                na::Vector2::new((r.min * k) as f32, (r.max * k) as f32)
+            }
+            None => na::Vector2::zeros(),
+        };
+        g.progs[0].set_vec2("height_range", &height_range)?;
 
-        if self.lighting && s != z {
-            if let Some(a) = &self.atm {
-                g.progs[3].use_gl();
-                unsafe {
-                    gl::Enable(gl::BLEND);
-                    // TODO this is certainly wrong
-                    gl::BlendFunc(gl::ONE, gl::ONE);
-                }
-                g.progs[3].set_mat4("model", &self.mat32(Some(&a)))?;
-                g.progs[3].set_mat4("view", &view)?;
-                g.progs[3].set_mat4("projection", &projection)?;
-                g.progs[3].set_vec3("sun_dir", &s)?;
-                g.progs[3].set_vec3("cam_pos", &(c.position * 1e6))?;
-                g.progs[3].set_vec3("pos", &(c.position * 1e6))?; // TODO
-                g.progs[3].set_float("Re", (self.r_equatorial * 1e6) as f32)?;
-                g.progs[3].set_float("Ra", ((self.r_equatorial + a.offset) * 1e6) as f32)?;
-                g.progs[3].set_float("Hr", a.scale_height_r as f32)?;
-                g.progs[3].set_vec3("betaR", &a.scatter_coeff_r)?;
-                g.progs[3].set_float("intensity", a.sun_intensity as f32)?;
+        match &self.terrain {
+            Some(terrain) => terrain.draw(g, c, &(tf * self.tf))?,
+            None => {
+                self.texture.bind(0);
                 g.meshes[0].draw();
-                unsafe { gl::Disable(gl::BLEND) }
-                g.progs[3].unuse_gl();
             }
         }
+        g.progs[0].unuse_gl();
 
         Ok(())
     }
+
+    fn update(&mut self, epoch: sputils::time::TDB) {
+        if let Some(tf) = self.source.as_mut().and_then(|s| s.transform(epoch)) {
+            self.tf = tf;
+        }
+        if let Some(cl) = &mut self.clouds {
+            let t = crate::clock::seconds_since_j2000(epoch);
+            cl.angle = (cl.rotation_rate * t).rem_euclid(std::f64::consts::TAU);
+        }
+        // Metres, like the scattering coefficients
+        let ground = self.r_equatorial * 1e6;
+        let medium = self
+            .atm
+            .as_ref()
+            .map(|a| a.medium(ground, ground + a.offset * 1e6));
+        // Remade only when anything they depend on has changed
+        match medium {
+            Some(m) if !matches!(&self.luts, Some(l) if l.medium == m) => {
+                self.luts = Some(super::atmosphere::Luts::new(m));
+            }
+            Some(_) => {}
+            None => self.luts = None,
+        }
+    }
+
+    fn update_view(
+        &mut self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+    ) {
+        if let Some(terrain) = &mut self.terrain {
+            terrain.update(g, c, &(tf * self.tf));
+        }
+    }
+
+    fn passes(&self) -> &'static [Pass] {
+        if self.atm.is_some() || self.clouds.is_some() {
+            &[Pass::Opaque, Pass::Atmosphere]
+        } else {
+            &[Pass::Opaque]
+        }
+    }
+
+    fn origin(&self) -> na::Vector3<f64> {
+        self.position()
+    }
+}
+
+/// How `Points` are joined when drawn as a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// Each point to the next
+    Strip,
+    /// Separate pairs, 0-1, 2-3, ...
+    Segments,
+    /// Strip with the last point joined back to the first
+    Loop,
+}
+
+/// Pattern along a line, lengths are in units along the line so it stays put as the camera moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineStyle {
+    Solid,
+    Dashed {
+        dash: f32,
+        gap: f32,
+    },
+    /// Round dots as wide as the line, `spacing` apart
+    Dotted {
+        spacing: f32,
+    },
+}
+
+/// Texture split into a grid of equal icons, numbered along rows from the top left
+pub struct Atlas {
+    pub texture: super::texture::Texture,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+/// Shape drawn for each of `Points` when not drawn as a line
+pub enum Marker {
+    Square,
+    /// Antialiased circle
+    Disc,
+    /// Icon from the atlas chosen per point by `Points::set_icons`, tinted by the colours
+    Icon(Atlas),
+}
+
+#[repr(C)]
+struct Vertex {
+    position: [f32; 3],
+    // RGBA bytes
+    color: [u8; 4],
+    // Units along the line from its start, for patterns
+    distance: f32,
+    // Multiplies `Points::width`
+    size: f32,
+    // Atlas cell
+    icon: f32,
 }
 
-// Consider a base transform
-// That way less modifications are needed for say
-// Following a planet around
-// Little to no performance change
 // TODO consider not keeping the Vec around
 // Just take a reference, set it and be done with it
 // That way there's no silly games with keeping it synced
@@ -144,29 +612,56 @@ impl Drawable for Planet {
 // I reckon do it
 pub struct Points {
     pub color: u32, // RGBA
+    /// Pixels, point size or line width
     pub width: f32,
     pub line: bool,
+    pub style: LineStyle,
+    pub marker: Marker,
+    /// Points are directions drawn at infinity in the sky pass, like the skybox.
+    ///
+    /// Only the rotation of `tf` is used, and they aren't drawn in orthographic views
+    pub at_infinity: bool,
+    /// Model transformation of all the points, keep them close to its origin for precision
+    pub tf: na::Matrix4<f64>,
     // Private so GL stuff can be generated once per modification
     points: Vec<na::Vector3<f32>>,
+    colors: Option<Vec<u32>>,
+    sizes: Option<Vec<f32>>,
+    icons: Option<Vec<u32>>,
+    topology: Topology,
     vbo: u32,
+    // A sprite per instance
     vao: u32,
+    // Same buffer read a segment per instance
+    line_vao: u32,
 }
 
 impl Points {
     pub fn new(color: u32, width: f32, line: bool, points: Vec<na::Vector3<f32>>) -> Self {
         let mut vbo: u32 = 0;
         let mut vao: u32 = 0;
+        let mut line_vao: u32 = 0;
         unsafe {
             gl::GenVertexArrays(1, &mut vao);
+            gl::GenVertexArrays(1, &mut line_vao);
             gl::GenBuffers(1, &mut vbo);
         }
         let mut s = Self {
             color,
             width,
             line,
+            style: LineStyle::Solid,
+            marker: Marker::Square,
+            at_infinity: false,
+            tf: na::Matrix4::identity(),
             points,
+            colors: None,
+            sizes: None,
+            icons: None,
+            topology: Topology::Strip,
             vbo,
             vao,
+            line_vao,
         };
         s.update_points();
         s
@@ -198,30 +693,300 @@ impl Points {
         self.update_points();
     }
 
+    pub fn get_colors(&self) -> Option<&[u32]> {
+        self.colors.as_deref()
+    }
+
+    /// RGBA per point, multiplied by `color`, `None` for all white.
+    ///
+    /// Missing colours (a shorter Vec than the points) are white.
+    pub fn set_colors(&mut self, colors: Option<Vec<u32>>) {
+        self.colors = colors;
+        self.update_points();
+    }
+
+    /// Like `modify_points`, starting from white for every point if there are no colours
+    pub fn modify_colors<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Vec<u32>),
+    {
+        let n = self.points.len();
+        f(self.colors.get_or_insert_with(|| vec![0xFFFFFFFF; n]));
+        self.update_points();
+    }
+
+    pub fn get_sizes(&self) -> Option<&[f32]> {
+        self.sizes.as_deref()
+    }
+
+    /// Marker size per point, multiplied by `width`, `None` for all 1.
+    ///
+    /// Missing sizes are 1, lines ignore them.
+    pub fn set_sizes(&mut self, sizes: Option<Vec<f32>>) {
+        self.sizes = sizes;
+        self.update_points();
+    }
+
+    /// Like `modify_points`, starting from 1 for every point if there are no sizes
+    pub fn modify_sizes<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Vec<f32>),
+    {
+        let n = self.points.len();
+        f(self.sizes.get_or_insert_with(|| vec![1.0; n]));
+        self.update_points();
+    }
+
+    pub fn get_icons(&self) -> Option<&[u32]> {
+        self.icons.as_deref()
+    }
+
+    /// Atlas cell per point for `Marker::Icon`, `None` (or missing ones) for the first
+    pub fn set_icons(&mut self, icons: Option<Vec<u32>>) {
+        self.icons = icons;
+        self.update_points();
+    }
+
+    /// Like `modify_points`, starting from 0 for every point if there are no icons
+    pub fn modify_icons<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Vec<u32>),
+    {
+        let n = self.points.len();
+        f(self.icons.get_or_insert_with(|| vec![0; n]));
+        self.update_points();
+    }
+
+    pub fn get_topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.update_points();
+    }
+
+    // Line segments drawn, one instance each
+    fn segments(&self) -> usize {
+        let n = self.points.len();
+        match self.topology {
+            Topology::Strip => n - 1,
+            Topology::Segments => n / 2,
+            Topology::Loop => n,
+        }
+    }
+
     fn update_points(&mut self) {
         if self.points.is_empty() {
             panic!("bruh");
         }
+        let color = |i: usize| {
+            self.colors
+                .as_ref()
+                .and_then(|c| c.get(i))
+                .map_or([0xFF; 4], |c| c.to_be_bytes())
+        };
+        let size = |i: usize| {
+            self.sizes
+                .as_ref()
+                .and_then(|s| s.get(i))
+                .map_or(1.0, |s| *s)
+        };
+        let icon = |i: usize| {
+            self.icons
+                .as_ref()
+                .and_then(|c| c.get(i))
+                .map_or(0.0, |c| *c as f32)
+        };
+        let mut vertices = Vec::with_capacity(self.points.len() + 1);
+        let mut distance = 0.0;
+        for (i, p) in self.points.iter().enumerate() {
+            if i > 0 {
+                // Pairs don't continue from each other
+                distance = if self.topology == Topology::Segments && i % 2 == 0 {
+                    0.0
+                } else {
+                    distance + (p - self.points[i - 1]).norm()
+                };
+            }
+            vertices.push(Vertex {
+                position: [p.x, p.y, p.z],
+                color: color(i),
+                distance,
+                size: size(i),
+                icon: icon(i),
+            });
+        }
+        if self.topology == Topology::Loop {
+            let p = self.points[0];
+            distance += (p - self.points[self.points.len() - 1]).norm();
+            vertices.push(Vertex {
+                position: [p.x, p.y, p.z],
+                color: color(0),
+                distance,
+                size: size(0),
+                icon: icon(0),
+            });
+        }
+
+        let size = std::mem::size_of::<Vertex>() as i32;
+        // A segment starts every vertex, or every other one for pairs
+        let step = if self.topology == Topology::Segments {
+            2 * size
+        } else {
+            size
+        };
         unsafe {
-            gl::BindVertexArray(self.vao);
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-
-            // I pray the layout of Vector3 is normal
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                std::mem::size_of_val(self.points.as_slice()) as isize,
-                self.points.as_ptr() as *const _,
+                std::mem::size_of_val(vertices.as_slice()) as isize,
+                vertices.as_ptr() as *const _,
                 // Might be nice to be able to set this
                 gl::DYNAMIC_DRAW,
             );
 
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 12, std::ptr::null());
-            gl::EnableVertexAttribArray(0);
+            // Position, colour, size and icon of each sprite
+            gl::BindVertexArray(self.vao);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, size, std::ptr::null());
+            gl::VertexAttribPointer(1, 4, gl::UNSIGNED_BYTE, gl::TRUE, size, 12 as *const _);
+            gl::VertexAttribPointer(2, 1, gl::FLOAT, gl::FALSE, size, 20 as *const _);
+            gl::VertexAttribPointer(3, 1, gl::FLOAT, gl::FALSE, size, 24 as *const _);
+            for l in 0..4 {
+                gl::EnableVertexAttribArray(l);
+                gl::VertexAttribDivisor(l, 1);
+            }
+
+            gl::BindVertexArray(self.line_vao);
+            line_attributes(size, step, 0);
 
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::BindVertexArray(0);
         }
     }
+
+    // Screen-space quads around each segment, shaped in the fragment shader
+    fn draw_line(
+        &self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        model: &na::Matrix4<f32>,
+        view: &na::Matrix4<f32>,
+        far_depth: f32,
+        color: &na::Vector4<f32>,
+    ) -> Result<(), std::ffi::NulError> {
+        let segments = self.segments();
+        if segments == 0 {
+            return Ok(());
+        }
+        let mut viewport = [0_i32; 4];
+        unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+        let (style, pattern) = match self.style {
+            LineStyle::Solid => (0, na::Vector2::zeros()),
+            LineStyle::Dashed { dash, gap } => (1, na::Vector2::new(dash, gap)),
+            LineStyle::Dotted { spacing } => (2, na::Vector2::new(spacing, 0.0)),
+        };
+        let projection = c.projection_matrix(g.aspect_ratio());
+        g.progs[6].use_gl();
+        g.progs[6].set_mat4("model", model)?;
+        g.progs[6].set_mat4("view", view)?;
+        g.progs[6].set_mat4("projection", &projection)?;
+        g.progs[6].set_depth(c)?;
+        g.progs[6].set_float("far_depth", far_depth)?;
+        g.progs[6].set_vec4("color", color)?;
+        g.progs[6].set_vec4("viewport", &na::convert(na::Vector4::from(viewport)))?;
+        g.progs[6].set_float("width", self.width)?;
+        g.progs[6].set_int("style", style)?;
+        g.progs[6].set_vec2("pattern", &pattern)?;
+        unsafe {
+            gl::BindVertexArray(self.line_vao);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, segments as i32);
+            gl::Disable(gl::BLEND);
+            gl::BindVertexArray(0);
+        }
+        g.progs[6].unuse_gl();
+        Ok(())
+    }
+
+    // Screen-space quad per point
+    fn draw_sprites(
+        &self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        model: &na::Matrix4<f32>,
+        view: &na::Matrix4<f32>,
+        far_depth: f32,
+        color: &na::Vector4<f32>,
+    ) -> Result<(), std::ffi::NulError> {
+        let mut viewport = [0_i32; 4];
+        unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+        let (shape, grid) = match &self.marker {
+            Marker::Square => (0, na::Vector2::new(1.0, 1.0)),
+            Marker::Disc => (1, na::Vector2::new(1.0, 1.0)),
+            Marker::Icon(a) => {
+                a.texture.bind(0);
+                (2, na::Vector2::new(a.columns as f32, a.rows as f32))
+            }
+        };
+        let projection = c.projection_matrix(g.aspect_ratio());
+        g.progs[7].use_gl();
+        g.progs[7].set_mat4("model", model)?;
+        g.progs[7].set_mat4("view", view)?;
+        g.progs[7].set_mat4("projection", &projection)?;
+        g.progs[7].set_depth(c)?;
+        g.progs[7].set_float("far_depth", far_depth)?;
+        g.progs[7].set_vec4("color", color)?;
+        g.progs[7].set_vec4("viewport", &na::convert(na::Vector4::from(viewport)))?;
+        g.progs[7].set_float("width", self.width)?;
+        g.progs[7].set_int("shape", shape)?;
+        g.progs[7].set_vec2("grid", &grid)?;
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, self.points.len() as i32);
+            gl::Disable(gl::BLEND);
+            gl::BindVertexArray(0);
+        }
+        g.progs[7].unuse_gl();
+        Ok(())
+    }
+}
+
+/// Points the bound vertex array's attributes for line.vert.glsl at the bound buffer, one
+/// segment every `step` bytes from byte `first`, joining a vertex to the one after it.
+///
+/// Vertices are `size` bytes, starting with a position, an RGBA8 colour and a distance along
+/// the line.
+pub(crate) unsafe fn line_attributes(size: i32, step: i32, first: usize) {
+    // Ends of the segment, then their colours and distances
+    for end in 0..2 {
+        let offset = first + (end * size) as usize;
+        let loc = 3 * end as u32;
+        gl::VertexAttribPointer(loc, 3, gl::FLOAT, gl::FALSE, step, offset as *const _);
+        gl::VertexAttribPointer(
+            loc + 1,
+            4,
+            gl::UNSIGNED_BYTE,
+            gl::TRUE,
+            step,
+            (offset + 12) as *const _,
+        );
+        gl::VertexAttribPointer(
+            loc + 2,
+            1,
+            gl::FLOAT,
+            gl::FALSE,
+            step,
+            (offset + 16) as *const _,
+        );
+        for l in loc..loc + 3 {
+            gl::EnableVertexAttribArray(l);
+            gl::VertexAttribDivisor(l, 1);
+        }
+    }
 }
 
 impl Drawable for Points {
@@ -229,6 +994,8 @@ impl Drawable for Points {
         &mut self,
         g: &mut super::Graphics,
         c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        _pass: Pass,
         _s: na::Vector3<f32>,
         #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))] _t: &mut Vec<(
             na::Vector3<f64>,
@@ -236,15 +1003,9 @@ impl Drawable for Points {
             imgui::ImString,
         )>,
     ) -> Result<(), std::ffi::NulError> {
-        // TODO let user provide this
-        let model: na::Matrix4<f32> = na::Matrix4::identity();
-        let view = c.view_matrix();
-        let projection = c.projection_matrix(g.aspect_ratio());
-        // Wrong program for this
-        g.progs[2].use_gl();
-        g.progs[2].set_mat4("model", &model)?;
-        g.progs[2].set_mat4("view", &view)?;
-        g.progs[2].set_mat4("projection", &projection)?;
+        if self.at_infinity && c.is_orthographic() {
+            return Ok(());
+        }
         let comp: [u8; 4] = unsafe { std::mem::transmute(self.color) };
         let v = 0xFF as f32;
         let color = na::Vector4::new(
@@ -253,39 +1014,57 @@ impl Drawable for Points {
             comp[1] as f32 / v,
             comp[0] as f32 / v,
         );
-        g.progs[2].set_vec4("color", &color)?;
-        unsafe {
-            // TODO not working :(
-            // Probably need to draw it as some kind of mesh
-            // Not epic
-            if self.line {
-                //gl::LineWidth(self.width);
+        if !self.at_infinity {
+            let model = c.relative(&(tf * self.tf));
+            let view = c.view_matrix();
+            return if self.line {
+                self.draw_line(g, c, &model, &view, -1.0, &color)
             } else {
-                gl::PointSize(self.width);
-            }
-            gl::BindVertexArray(self.vao);
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-            gl::DrawArrays(
-                // TODO GL_LINES, GL_LINE_LOOP
-                // TODO line styles eg stipple
-                if self.line {
-                    gl::LINE_STRIP
-                } else {
-                    gl::POINTS
-                },
-                0,
-                self.points.len() as i32,
-            );
-            gl::Disable(gl::BLEND);
-            gl::BindVertexArray(0);
+                self.draw_sprites(g, c, &model, &view, -1.0, &color)
+            };
+        }
+        let mut model: na::Matrix4<f32> = na::convert(tf * self.tf);
+        model.m14 = 0.0;
+        model.m24 = 0.0;
+        model.m34 = 0.0;
+        let view = c.rot_matrix();
+        let far_depth = g.begin_sky();
+        unsafe { gl::DepthMask(gl::FALSE) };
+        let r = if self.line {
+            self.draw_line(g, c, &model, &view, far_depth, &color)
+        } else {
+            self.draw_sprites(g, c, &model, &view, far_depth, &color)
+        };
+        unsafe { gl::DepthMask(gl::TRUE) };
+        g.end_sky();
+        r
+    }
+
+    fn passes(&self) -> &'static [Pass] {
+        if self.at_infinity {
+            &[Pass::Sky]
+        } else {
+            &[Pass::Transparent]
+        }
+    }
+
+    fn origin(&self) -> na::Vector3<f64> {
+        na::Vector3::new(self.tf.m14, self.tf.m24, self.tf.m34)
+    }
+}
+
+impl Drop for Points {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteVertexArrays(1, &self.line_vao);
         }
-        g.progs[2].unuse_gl();
-        Ok(())
     }
 }
 
 pub struct Text {
+    /// In the frame of the scene node it is attached to, if any
     pub position: na::Vector3<f64>,
     pub color: u32,
     pub text: String,
@@ -297,11 +1076,13 @@ impl Drawable for Text {
         &mut self,
         g: &mut super::Graphics,
         c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        _pass: Pass,
         _s: na::Vector3<f32>,
         t: &mut Vec<(na::Vector3<f64>, u32, imgui::ImString)>,
     ) -> Result<(), std::ffi::NulError> {
         t.push((
-            self.position.clone_owned(),
+            (tf * self.position.push(1.0)).xyz(),
             self.color,
             imgui::ImString::new(self.text.clone()),
         ));
@@ -313,8 +1094,18 @@ impl Drawable for Text {
         &mut self,
         g: &mut super::Graphics,
         c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        _pass: Pass,
         _s: na::Vector3<f32>,
     ) -> Result<(), std::ffi::NulError> {
         Ok(())
     }
+
+    fn passes(&self) -> &'static [Pass] {
+        &[Pass::Labels]
+    }
+
+    fn origin(&self) -> na::Vector3<f64> {
+        self.position
+    }
 }
@@ -0,0 +1,308 @@
+// Coordinate grids on the sky, oriented for the simulation epoch
+use super::drawable::{Drawable, Pass, Points, Topology};
+use sputils::coord::{Observer, GCRS, ICRS};
+use sputils::time::{TT, UT1};
+use std::f64::consts::PI;
+
+const DEG: f64 = PI / 180.0;
+// Grid lines are split into pieces this long (degrees)
+const STEP: f64 = 2.0;
+// Precession is recomputed when the epoch moves this far (seconds), the horizon always is
+const REORIENT_SECONDS: f64 = 86400.0;
+
+/// Coordinates a `Grid` is drawn in
+#[derive(Debug, Clone, Copy)]
+pub enum GridFrame {
+    /// Right ascension and declination, mean equator and equinox of date
+    Equatorial,
+    /// Ecliptic longitude and latitude, mean ecliptic and equinox of date
+    Ecliptic,
+    /// Galactic longitude and latitude
+    Galactic,
+    /// Azimuth (from north through east) and altitude for an observer on the Earth
+    Horizon(Observer),
+}
+
+impl GridFrame {
+    /// Rotation from ICRS to the frame
+    pub fn matrix(&self, epoch: sputils::time::TDB) -> na::Matrix3<f64> {
+        // TDB-TT is under 2 ms
+        let tt = TT::from_tdb(epoch, 0.0);
+        match self {
+            Self::Equatorial => GCRS::gcrs_to_mean_mat(&tt),
+            Self::Ecliptic => GCRS::gcrs_to_ecliptic_mat(&tt),
+            Self::Galactic => ICRS::icrs_to_galactic_mat(),
+            Self::Horizon(o) => {
+                // UT1 is taken as UTC, and as TT outside the range of UTC
+                let ut1 = sputils::almanac::tt_to_utc(tt)
+                    .and_then(|u| u.try_into_ut1(0.0))
+                    .unwrap_or(UT1(tt.0, tt.1));
+                // Directions, so the observer's offset from the geocentre doesn't matter
+                o.enu_mat() * GCRS::gcrs_to_itrs_mat(&tt, &ut1, 0.0, 0.0)
+            }
+        }
+    }
+
+    fn lon_label(&self, deg: f64) -> String {
+        match self {
+            Self::Equatorial => format!("{}h", (deg / 15.0).round()),
+            _ => format!("{}°", deg.round()),
+        }
+    }
+
+    fn fundamental(&self) -> &'static str {
+        match self {
+            Self::Equatorial => "Equator",
+            Self::Ecliptic => "Ecliptic",
+            Self::Galactic => "Galactic equator",
+            Self::Horizon(_) => "Horizon",
+        }
+    }
+}
+
+// Unit vector in the frame, longitude anticlockwise from +X except for azimuth
+fn frame_direction(frame: &GridFrame, lon: f64, lat: f64) -> na::Vector3<f64> {
+    let (sl, cl) = (lon * DEG).sin_cos();
+    let (sb, cb) = (lat * DEG).sin_cos();
+    match frame {
+        // East, north, up
+        GridFrame::Horizon(_) => na::Vector3::new(cb * sl, cb * cl, sb),
+        _ => na::Vector3::new(cb * cl, cb * sl, sb),
+    }
+}
+
+/// Meridians and parallels at infinity with the stars, the fundamental circle (equator,
+/// ecliptic or horizon) highlighted, and labels along it.
+///
+/// Horizon grids also mark the cardinal points.
+pub struct Grid {
+    pub visible: bool,
+    pub color: u32, // RGBA
+    /// Colour of the fundamental circle and the cardinal points
+    pub highlight: u32, // RGBA
+    /// Pixels
+    pub width: f32,
+    pub labels: bool,
+    frame: GridFrame,
+    // Degrees between meridians and between parallels
+    spacing: (f64, f64),
+    // Seconds past J2000 the orientation is for
+    oriented: Option<f64>,
+    // Frame to ICRS
+    rotation: na::Matrix3<f64>,
+    lines: Points,
+    fundamental: Points,
+}
+
+impl Grid {
+    pub fn new(frame: GridFrame) -> Self {
+        let spacing = match frame {
+            GridFrame::Equatorial => (15.0, 10.0),
+            _ => (30.0, 10.0),
+        };
+        let mut lines = Points::new(0xFFFFFFFF, 1.0, true, Self::lines(&frame, spacing));
+        lines.set_topology(Topology::Segments);
+        lines.at_infinity = true;
+        let circle = (0..(360.0 / STEP) as usize)
+            .map(|i| na::convert(frame_direction(&frame, i as f64 * STEP, 0.0)))
+            .collect();
+        let mut fundamental = Points::new(0xFFFFFFFF, 1.0, true, circle);
+        fundamental.set_topology(Topology::Loop);
+        fundamental.at_infinity = true;
+        let (color, highlight) = match frame {
+            GridFrame::Equatorial => (0x3060A060, 0x4080E0C0),
+            GridFrame::Ecliptic => (0xA0802060, 0xE0B030C0),
+            GridFrame::Galactic => (0xA040A060, 0xE060E0C0),
+            GridFrame::Horizon(_) => (0x40A04060, 0x60E060C0),
+        };
+        Self {
+            visible: true,
+            color,
+            highlight,
+            width: 1.0,
+            labels: true,
+            frame,
+            spacing,
+            oriented: None,
+            rotation: na::Matrix3::identity(),
+            lines,
+            fundamental,
+        }
+    }
+
+    /// Degrees between meridians and between parallels
+    pub fn spacing(&self) -> (f64, f64) {
+        self.spacing
+    }
+
+    pub fn set_spacing(&mut self, lon: f64, lat: f64) {
+        self.spacing = (lon, lat);
+        self.lines
+            .set_points(Self::lines(&self.frame, self.spacing));
+    }
+
+    pub fn frame(&self) -> &GridFrame {
+        &self.frame
+    }
+
+    /// Takes effect at the next update, such as to move the observer
+    pub fn set_frame(&mut self, frame: GridFrame) {
+        self.frame = frame;
+        self.oriented = None;
+        self.lines
+            .set_points(Self::lines(&self.frame, self.spacing));
+    }
+
+    // Pairs along every meridian and parallel, leaving out the fundamental circle
+    fn lines(frame: &GridFrame, (lon_step, lat_step): (f64, f64)) -> Vec<na::Vector3<f32>> {
+        let mut points = Vec::new();
+        let mut push = |a: na::Vector3<f64>, b: na::Vector3<f64>| {
+            points.push(na::convert(a));
+            points.push(na::convert(b));
+        };
+        let meridians = (360.0 / lon_step).round().max(1.0) as usize;
+        for m in 0..meridians {
+            let lon = m as f64 * lon_step;
+            let n = (180.0 / STEP) as usize;
+            for i in 0..n {
+                let lat = -90.0 + i as f64 * STEP;
+                push(
+                    frame_direction(frame, lon, lat),
+                    frame_direction(frame, lon, lat + STEP),
+                );
+            }
+        }
+        let parallels = (90.0 / lat_step).ceil() as usize;
+        for p in 1..parallels {
+            for lat in [p as f64 * lat_step, -(p as f64) * lat_step].iter() {
+                let n = (360.0 / STEP) as usize;
+                for i in 0..n {
+                    let lon = i as f64 * STEP;
+                    push(
+                        frame_direction(frame, lon, *lat),
+                        frame_direction(frame, lon + STEP, *lat),
+                    );
+                }
+            }
+        }
+        points
+    }
+
+    // Text and frame direction of every label, and whether it is highlighted
+    fn label_list(&self) -> Vec<(String, na::Vector3<f64>, bool)> {
+        let (lon_step, lat_step) = self.spacing;
+        let mut labels = Vec::new();
+        let cardinal = matches!(self.frame, GridFrame::Horizon(_));
+        let meridians = (360.0 / lon_step).round().max(1.0) as usize;
+        for m in 0..meridians {
+            let lon = m as f64 * lon_step;
+            if cardinal && (lon % 90.0).abs() < 1e-9 {
+                continue;
+            }
+            // Just off the fundamental circle so it isn't covered
+            labels.push((
+                self.frame.lon_label(lon),
+                frame_direction(&self.frame, lon, 1.0),
+                false,
+            ));
+        }
+        let parallels = (90.0 / lat_step).ceil() as usize;
+        for p in 1..parallels {
+            let lat = p as f64 * lat_step;
+            for lat in [lat, -lat].iter() {
+                labels.push((
+                    format!("{:+}°", lat.round()),
+                    frame_direction(&self.frame, 0.0, *lat),
+                    false,
+                ));
+            }
+        }
+        if cardinal {
+            for (i, name) in ["N", "E", "S", "W"].iter().enumerate() {
+                labels.push((
+                    name.to_string(),
+                    frame_direction(&self.frame, i as f64 * 90.0, 0.0),
+                    true,
+                ));
+            }
+        } else {
+            labels.push((
+                self.frame.fundamental().to_owned(),
+                frame_direction(&self.frame, lon_step / 2.0, 0.0),
+                true,
+            ));
+        }
+        labels
+    }
+}
+
+impl Drawable for Grid {
+    fn draw(
+        &mut self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        pass: Pass,
+        s: na::Vector3<f32>,
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))] t: &mut Vec<(
+            na::Vector3<f64>,
+            u32,
+            imgui::ImString,
+        )>,
+    ) -> Result<(), std::ffi::NulError> {
+        // No sky in diagrams
+        if !self.visible || c.is_orthographic() {
+            return Ok(());
+        }
+        if pass == Pass::Labels {
+            #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+            if self.labels {
+                for (text, d, highlight) in self.label_list() {
+                    // Far enough that moving the camera doesn't move the label
+                    let p = c.position + tf.transform_vector(&(self.rotation * d)) * 1e9;
+                    let color = if highlight {
+                        self.highlight
+                    } else {
+                        self.color
+                    };
+                    t.push((p, color | 0xFF, imgui::ImString::new(text)));
+                }
+            }
+            return Ok(());
+        }
+        self.lines.color = self.color;
+        self.lines.width = self.width;
+        self.fundamental.color = self.highlight;
+        self.fundamental.width = self.width * 2.0;
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+        {
+            self.lines.draw(g, c, tf, pass, s, t)?;
+            self.fundamental.draw(g, c, tf, pass, s, t)
+        }
+        #[cfg(any(target_os = "emscripten", feature = "gles"))]
+        {
+            self.lines.draw(g, c, tf, pass, s)?;
+            self.fundamental.draw(g, c, tf, pass, s)
+        }
+    }
+
+    fn update(&mut self, epoch: sputils::time::TDB) {
+        let seconds = crate::clock::seconds_since_j2000(epoch);
+        let stale = match (self.oriented, self.frame) {
+            (None, _) => true,
+            (Some(o), GridFrame::Horizon(_)) => o != seconds,
+            (Some(o), _) => (seconds - o).abs() > REORIENT_SECONDS,
+        };
+        if stale {
+            self.oriented = Some(seconds);
+            self.rotation = self.frame.matrix(epoch).transpose();
+            let tf = sputils::orient::homogeneous(&self.rotation);
+            self.lines.tf = tf;
+            self.fundamental.tf = tf;
+        }
+    }
+
+    fn passes(&self) -> &'static [Pass] {
+        &[Pass::Sky, Pass::Labels]
+    }
+}
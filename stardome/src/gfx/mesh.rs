@@ -8,9 +8,7 @@ pub struct Mesh {
 
 use std::f32::consts::{PI, TAU};
 
-// Cube-sphere would be nice
-// Less distortion around poles
-// And easy use of cubemap
+// Planets with terrain use a cube-sphere instead, see terrain.rs
 impl Mesh {
     // http://www.songho.ca/opengl/gl_sphere.html
     #[allow(clippy::many_single_char_names)]
@@ -19,7 +17,7 @@ impl Mesh {
         if radius <= 0.0 || h_div < 3 || v_div < 2 {
             panic!("Invalid parameters");
         }
-        let mut vertices: Vec<f32> = Vec::with_capacity(8 * ((h_div + 1) * (v_div + 1)) as usize);
+        let mut vertices: Vec<f32> = Vec::with_capacity(11 * ((h_div + 1) * (v_div + 1)) as usize);
         let mut indices: Vec<u32> = Vec::with_capacity(6 * h_div as usize * (v_div as usize - 1));
 
         let h_step: f32 = TAU / h_div as f32;
@@ -56,6 +54,10 @@ impl Mesh {
                 vertices.push(nz);
                 vertices.push(s);
                 vertices.push(t);
+                // East, the direction of increasing s
+                vertices.push(h_angle.sin());
+                vertices.push(-h_angle.cos());
+                vertices.push(0.0);
             }
         }
 
@@ -138,6 +140,7 @@ impl Mesh {
         }
     }
 
+    /// Vertices are position, normal, texture coordinates and tangent (11 floats)
     pub unsafe fn load_gl(vertices: &[f32], indices: &[u32]) -> Self {
         let mut vbo: u32 = 0;
         let mut vao: u32 = 0;
@@ -165,12 +168,14 @@ impl Mesh {
             gl::STATIC_DRAW,
         );
 
-        gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 32, std::ptr::null());
+        gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 44, std::ptr::null());
         gl::EnableVertexAttribArray(0);
-        gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, 32, 12 as *const _);
+        gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, 44, 12 as *const _);
         gl::EnableVertexAttribArray(1);
-        gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, 32, 24 as *const _);
+        gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, 44, 24 as *const _);
         gl::EnableVertexAttribArray(2);
+        gl::VertexAttribPointer(3, 3, gl::FLOAT, gl::FALSE, 44, 32 as *const _);
+        gl::EnableVertexAttribArray(3);
 
         //gl::BindVertexArray(0);
         //gl::BindBuffer(gl::ARRAY_BUFFER, 0);
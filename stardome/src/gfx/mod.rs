@@ -1,22 +1,43 @@
+pub mod atmosphere;
 pub mod camera;
+pub mod constellations;
+pub mod controller;
+pub mod dome;
 pub mod drawable;
+pub mod grid;
 pub mod libs;
 pub mod material;
 pub mod mesh;
+pub mod orbit;
+pub mod scene;
 pub mod shader;
+pub mod stars;
+pub mod terrain;
 pub mod texture;
 
 pub struct Graphics {
     pub libs: libs::GraphicsLibs,
+    /// Programs for the current camera's depth mode, see `prepare_depth`
     pub progs: Vec<shader::Program>,
+    // The other variant of `progs`, with or without LOG_DEPTH
+    spare_progs: Vec<shader::Program>,
+    // Whether `progs` write logarithmic depth
+    log_progs: bool,
     pub meshes: Vec<mesh::Mesh>,
     pub textures: Vec<texture::Texture>,
     pub cubemap: texture::Cubemap,
+    /// Whether `ReversedZ` depth is available
+    pub clip_control: bool,
+    // Depth mode the GL state is set up for
+    depth: camera::DepthMode,
+    // Fisheye render target, created on first use
+    dome: Option<dome::Dome>,
+    // Whether the dome faces still hold the last frame
+    dome_stale: bool,
 }
 
 impl Graphics {
     pub fn new() -> crate::BoxResult<Self> {
-        use shader::{Program, Shader};
         let libs = libs::GraphicsLibs::load()?;
         let meshes = vec![mesh::Mesh::uv_sphere(1.0, 360, 180), mesh::Mesh::cube()];
         let textures = vec![/*
@@ -25,57 +46,21 @@ impl Graphics {
             texture::Texture::open("img/gen/moon_albedo.png")?,
         */];
         let cubemap = texture::Cubemap::open("img/gen/milky_way.png")?;
-        #[cfg(not(any(target_os = "emscripten", feature = "gles")))]
-        let progs = vec![
-            Program::new(&[
-                &Shader::vertex(include_bytes!("../glsl/planet.vert.glsl"))?,
-                &Shader::frag(include_bytes!("../glsl/planet.frag.glsl"))?,
-            ])?,
-            Program::new(&[
-                &Shader::vertex(include_bytes!("../glsl/box.vert.glsl"))?,
-                &Shader::frag(include_bytes!("../glsl/box.frag.glsl"))?,
-            ])?,
-            Program::new(&[
-                &Shader::vertex(include_bytes!("../glsl/simple.vert.glsl"))?,
-                &Shader::frag(include_bytes!("../glsl/simple.frag.glsl"))?,
-            ])?,
-            Program::new(&[
-                &Shader::vertex(include_bytes!("../glsl/atmosphere.vert.glsl"))?,
-                &Shader::frag(include_bytes!("../glsl/atmosphere.frag.glsl"))?,
-            ])?,
-        ];
-        #[cfg(any(target_os = "emscripten", feature = "gles"))]
-        let progs = vec![
-            Program::new(&[
-                &Shader::vertex(include_bytes!("../glsl/planet.es.vert.glsl"))?,
-                &Shader::frag(include_bytes!("../glsl/planet.es.frag.glsl"))?,
-            ])?,
-            Program::new(&[
-                &Shader::vertex(include_bytes!("../glsl/box.es.vert.glsl"))?,
-                &Shader::frag(include_bytes!("../glsl/box.es.frag.glsl"))?,
-            ])?,
-            Program::new(&[
-                &Shader::vertex(include_bytes!("../glsl/simple.es.vert.glsl"))?,
-                &Shader::frag(include_bytes!("../glsl/simple.es.frag.glsl"))?,
-            ])?,
-            Program::new(&[
-                &Shader::vertex(include_bytes!("../glsl/atmosphere.es.vert.glsl"))?,
-                &Shader::frag(include_bytes!("../glsl/atmosphere.es.frag.glsl"))?,
-            ])?,
-        ];
-        progs[0].use_gl();
-        progs[0].set_int("texture1", 0)?;
-        progs[0].unuse_gl();
-        progs[1].use_gl();
-        progs[1].set_int("skybox", 0)?;
-        progs[1].unuse_gl();
+        let progs = programs(&[])?;
+        let spare_progs = programs(&["LOG_DEPTH"])?;
 
         Ok(Self {
             libs,
             progs,
+            spare_progs,
+            log_progs: false,
             meshes,
             textures,
             cubemap,
+            clip_control: clip_control_supported(),
+            depth: camera::DepthMode::Standard,
+            dome: None,
+            dome_stale: true,
         })
     }
 
@@ -96,28 +81,268 @@ impl Graphics {
         false
     }
 
-    pub fn handle_frame(&mut self) {
+    /// Whether `mode` can be used, `ReversedZ` needs clip control
+    pub fn supports_depth(&self, mode: camera::DepthMode) -> bool {
+        mode != camera::DepthMode::ReversedZ || self.clip_control
+    }
+
+    /// Use the programs for the camera's depth mode, only logarithmic depth writes
+    /// `gl_FragDepth`
+    pub fn prepare_depth(&mut self, cam: &camera::Camera) {
+        if cam.is_log_depth() != self.log_progs {
+            std::mem::swap(&mut self.progs, &mut self.spare_progs);
+            self.log_progs = !self.log_progs;
+        }
+    }
+
+    /// Show the frame and clear for the next one, switching to `cam`'s depth mode first so
+    /// no pass loses the depth of the ones before
+    pub fn handle_frame(&mut self, cam: &camera::Camera) {
         self.libs.window.gl_swap_window();
+        self.set_depth_mode(cam.depth());
         unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT) }
+        self.dome_stale = true;
     }
 
-    pub fn draw_skybox(&mut self, cam: &camera::Camera, sun: &na::Vector3<f64>) {
-        // Cubemap
+    fn set_depth_mode(&mut self, mode: camera::DepthMode) {
+        if mode == self.depth {
+            return;
+        }
+        let reversed = mode == camera::DepthMode::ReversedZ;
         unsafe {
-            gl::DepthFunc(gl::LEQUAL);
+            if self.clip_control {
+                gl::ClipControl(
+                    gl::LOWER_LEFT,
+                    if reversed {
+                        gl::ZERO_TO_ONE
+                    } else {
+                        gl::NEGATIVE_ONE_TO_ONE
+                    },
+                );
+                // Only reachable with clip control, so never on GLES
+                gl::ClearDepth(if reversed { 0.0 } else { 1.0 });
+            }
+            gl::DepthFunc(if reversed { gl::GREATER } else { gl::LESS });
+        }
+        self.depth = mode;
+    }
+
+    /// Render into face `n` of the fisheye cubemap, cleared on the first bind of each frame
+    pub fn bind_dome_face(&mut self, n: u32) {
+        let d = self.dome.get_or_insert_with(dome::Dome::new);
+        if self.dome_stale {
+            d.clear();
+            self.dome_stale = false;
+        }
+        d.bind_face(n);
+    }
+
+    /// Back to drawing to the window
+    pub fn unbind_dome(&mut self) {
+        if let Some(d) = &self.dome {
+            d.unbind(self.libs.window.size());
+        }
+    }
+
+    /// Warp the fisheye cubemap onto the window
+    pub fn draw_dome(&mut self, fov: f32) {
+        let aspect_ratio = self.aspect_ratio();
+        if let Some(d) = &self.dome {
+            d.draw(&self.progs[4], fov, aspect_ratio);
         }
+    }
+
+    /// Depth testing for drawing on the far plane, behind everything but the skybox.
+    ///
+    /// Returns the far plane's depth in NDC, for `gl_Position.z / gl_Position.w`.
+    /// Call `prepare_depth` first and `end_sky` after.
+    pub fn begin_sky(&self) -> f32 {
+        let reversed = self.depth == camera::DepthMode::ReversedZ;
+        unsafe {
+            gl::DepthFunc(if reversed { gl::GEQUAL } else { gl::LEQUAL });
+        }
+        if reversed {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    pub fn end_sky(&self) {
+        let reversed = self.depth == camera::DepthMode::ReversedZ;
+        unsafe {
+            gl::DepthFunc(if reversed { gl::GREATER } else { gl::LESS });
+        }
+    }
+
+    pub fn draw_skybox(&mut self, cam: &camera::Camera, sun: &na::Vector3<f64>) {
+        self.prepare_depth(cam);
+        // Cubemap
+        let far_depth = self.begin_sky();
         self.progs[1].use_gl();
         let _ = self.progs[1].set_mat4("view", &cam.rot_matrix());
         let projection = cam.projection_matrix(self.aspect_ratio());
         let _ = self.progs[1].set_mat4("projection", &projection);
+        let _ = self.progs[1].set_float("far_depth", far_depth);
         let _ = self.progs[1].set_vec3("sun_dir", &na::convert(sun.normalize()));
         let _ = self.progs[1].set_float("sun_angle_rad", (695.700 / sun.magnitude()).atan() as f32);
         self.cubemap.bind(0);
         self.meshes[1].draw();
         self.progs[1].unuse_gl();
-        unsafe {
-            gl::DepthFunc(gl::LESS);
+        self.end_sky();
+    }
+}
+
+// Vertex and fragment shaders of each program, by index into `Graphics::progs`
+#[cfg(not(any(target_os = "emscripten", feature = "gles")))]
+const SOURCES: [(&[u8], &[u8]); 10] = [
+    (
+        include_bytes!("../glsl/planet.vert.glsl"),
+        include_bytes!("../glsl/planet.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/box.vert.glsl"),
+        include_bytes!("../glsl/box.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/simple.vert.glsl"),
+        include_bytes!("../glsl/simple.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/atmosphere.vert.glsl"),
+        include_bytes!("../glsl/atmosphere.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/dome.vert.glsl"),
+        include_bytes!("../glsl/dome.frag.glsl"),
+    ),
+    // Orbit trails, with TRAIL defined
+    (
+        include_bytes!("../glsl/line.vert.glsl"),
+        include_bytes!("../glsl/line.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/line.vert.glsl"),
+        include_bytes!("../glsl/line.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/sprite.vert.glsl"),
+        include_bytes!("../glsl/sprite.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/star.vert.glsl"),
+        include_bytes!("../glsl/star.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/planet.vert.glsl"),
+        include_bytes!("../glsl/clouds.frag.glsl"),
+    ),
+];
+#[cfg(any(target_os = "emscripten", feature = "gles"))]
+const SOURCES: [(&[u8], &[u8]); 10] = [
+    (
+        include_bytes!("../glsl/planet.es.vert.glsl"),
+        include_bytes!("../glsl/planet.es.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/box.es.vert.glsl"),
+        include_bytes!("../glsl/box.es.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/simple.es.vert.glsl"),
+        include_bytes!("../glsl/simple.es.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/atmosphere.es.vert.glsl"),
+        include_bytes!("../glsl/atmosphere.es.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/dome.es.vert.glsl"),
+        include_bytes!("../glsl/dome.es.frag.glsl"),
+    ),
+    // Orbit trails, with TRAIL defined
+    (
+        include_bytes!("../glsl/line.es.vert.glsl"),
+        include_bytes!("../glsl/line.es.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/line.es.vert.glsl"),
+        include_bytes!("../glsl/line.es.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/sprite.es.vert.glsl"),
+        include_bytes!("../glsl/sprite.es.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/star.es.vert.glsl"),
+        include_bytes!("../glsl/star.es.frag.glsl"),
+    ),
+    (
+        include_bytes!("../glsl/planet.es.vert.glsl"),
+        include_bytes!("../glsl/clouds.es.frag.glsl"),
+    ),
+];
+
+fn programs(defines: &[&str]) -> crate::BoxResult<Vec<shader::Program>> {
+    use shader::{Program, Shader};
+    let trail: Vec<&str> = defines.iter().copied().chain(Some("TRAIL")).collect();
+    let progs = SOURCES
+        .iter()
+        .enumerate()
+        .map(|(i, (v, f))| {
+            let defines = if i == 5 { &trail } else { defines };
+            Program::new(&[
+                &Shader::with_defines(gl::VERTEX_SHADER, v, defines)?,
+                &Shader::with_defines(gl::FRAGMENT_SHADER, f, defines)?,
+            ])
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    progs[0].use_gl();
+    progs[0].set_int("texture1", 0)?;
+    progs[0].set_int("night_texture", 1)?;
+    progs[0].set_int("cloud_texture", 2)?;
+    progs[0].set_int("normal_map", 3)?;
+    progs[0].set_int("specular_map", 4)?;
+    progs[0].set_int("height_map", 5)?;
+    progs[0].unuse_gl();
+    progs[1].use_gl();
+    progs[1].set_int("skybox", 0)?;
+    progs[1].unuse_gl();
+    progs[3].use_gl();
+    progs[3].set_int("transmittance_lut", 0)?;
+    progs[3].set_int("multiscattering_lut", 1)?;
+    progs[3].unuse_gl();
+    progs[4].use_gl();
+    progs[4].set_int("faces", 0)?;
+    progs[4].unuse_gl();
+    progs[7].use_gl();
+    progs[7].set_int("atlas", 0)?;
+    progs[7].unuse_gl();
+    progs[9].use_gl();
+    progs[9].set_int("clouds", 0)?;
+    progs[9].unuse_gl();
+    Ok(progs)
+}
+
+fn clip_control_supported() -> bool {
+    if !gl::ClipControl::is_loaded() {
+        return false;
+    }
+    unsafe {
+        let mut major = 0;
+        let mut minor = 0;
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        if (major, minor) >= (4, 5) {
+            return true;
         }
+        let mut n = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut n);
+        (0..n as u32).any(|i| {
+            let s = gl::GetStringi(gl::EXTENSIONS, i);
+            !s.is_null()
+                && std::ffi::CStr::from_ptr(s as *const _).to_bytes() == b"GL_ARB_clip_control"
+        })
     }
 }
 
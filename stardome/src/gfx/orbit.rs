@@ -0,0 +1,456 @@
+// Orbit trails sampled around the simulation clock
+// Samples are added and dropped at the ends as the clock moves, so only new ones are uploaded
+use super::drawable::{Drawable, Pass};
+use std::collections::VecDeque;
+use std::f64::consts::{PI, TAU};
+
+// Bounds the work done by one update, eg after a big jump in time
+const MAX_SAMPLES_PER_UPDATE: usize = 4096;
+// Times are stored as f32 seconds from an anchor, moved when they get this far apart
+const MAX_ANCHOR_AGE: f64 = 1e6;
+
+/// Position of something as a function of time, in the frame of the node its path is attached to
+pub trait Trajectory {
+    /// Position (units) at `t` TDB seconds past J2000, `None` where unknown
+    fn position(&mut self, t: f64) -> Option<na::Vector3<f64>>;
+
+    /// Longest time step (s) worth taking, eg a fraction of the period
+    fn max_step(&self) -> f64 {
+        3600.0
+    }
+}
+
+impl<F: FnMut(f64) -> na::Vector3<f64>> Trajectory for F {
+    fn position(&mut self, t: f64) -> Option<na::Vector3<f64>> {
+        Some(self(t))
+    }
+}
+
+/// Two-body osculating elements, elliptic or hyperbolic (not exactly parabolic)
+#[derive(Debug, Clone, Copy)]
+pub struct Elements {
+    /// Semi-major axis (km), negative for hyperbolic orbits
+    pub a: f64,
+    pub e: f64,
+    /// Inclination (radians)
+    pub i: f64,
+    /// Longitude of the ascending node (radians)
+    pub raan: f64,
+    /// Argument of periapsis (radians)
+    pub argp: f64,
+    /// Mean anomaly at `epoch` (radians)
+    pub m0: f64,
+    /// TDB seconds past J2000
+    pub epoch: f64,
+    /// Gravitational parameter of the primary (km^3/s^2)
+    pub gm: f64,
+}
+
+impl Elements {
+    /// Radians per second
+    pub fn mean_motion(&self) -> f64 {
+        (self.gm / self.a.abs().powi(3)).sqrt()
+    }
+
+    /// Seconds, `None` for hyperbolic orbits
+    pub fn period(&self) -> Option<f64> {
+        if self.e < 1.0 {
+            Some(TAU / self.mean_motion())
+        } else {
+            None
+        }
+    }
+
+    /// Position (km) at `t` TDB seconds past J2000, in the frame the elements are referred to
+    pub fn position_km(&self, t: f64) -> na::Vector3<f64> {
+        let m = self.m0 + self.mean_motion() * (t - self.epoch);
+        let e = self.e;
+        // Perifocal, x towards periapsis
+        let (x, y) = if e < 1.0 {
+            let ea = eccentric_anomaly((m + PI).rem_euclid(TAU) - PI, e);
+            (
+                self.a * (ea.cos() - e),
+                self.a * (1.0 - e * e).sqrt() * ea.sin(),
+            )
+        } else {
+            let h = hyperbolic_anomaly(m, e);
+            (
+                self.a * (h.cosh() - e),
+                -self.a * (e * e - 1.0).sqrt() * h.sinh(),
+            )
+        };
+        na::Rotation3::from_axis_angle(&na::Vector3::z_axis(), self.raan)
+            * na::Rotation3::from_axis_angle(&na::Vector3::x_axis(), self.i)
+            * na::Rotation3::from_axis_angle(&na::Vector3::z_axis(), self.argp)
+            * na::Vector3::new(x, y, 0.0)
+    }
+}
+
+impl Trajectory for Elements {
+    fn position(&mut self, t: f64) -> Option<na::Vector3<f64>> {
+        Some(self.position_km(t) / crate::KM_PER_UNIT)
+    }
+
+    fn max_step(&self) -> f64 {
+        self.period().map_or(86400.0, |p| p / 32.0)
+    }
+}
+
+// Kepler's equation M = E - e sin E, M in -PI..PI
+fn eccentric_anomaly(m: f64, e: f64) -> f64 {
+    let mut ea = if e > 0.8 { PI.copysign(m) } else { m };
+    for _ in 0..50 {
+        let d = (ea - e * ea.sin() - m) / (1.0 - e * ea.cos());
+        ea -= d;
+        if d.abs() < 1e-12 {
+            break;
+        }
+    }
+    ea
+}
+
+// Hyperbolic Kepler's equation M = e sinh H - H
+fn hyperbolic_anomaly(m: f64, e: f64) -> f64 {
+    let mut h = (m / e).asinh();
+    for _ in 0..50 {
+        let d = (e * h.sinh() - h - m) / (e * h.cosh() - 1.0);
+        h -= d;
+        if d.abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+// Laid out for line.vert.glsl, with the time in place of the distance along the line
+#[repr(C)]
+struct Vertex {
+    position: [f32; 3],
+    // Always white, the trail is coloured by `OrbitPath::color`
+    color: [u8; 4],
+    // Seconds from `OrbitPath::anchor`
+    time: f32,
+}
+
+/// Trail of a `Trajectory` around the simulation clock, fading with age.
+///
+/// Sampled with steps that shrink where the path turns sharply (eg near periapsis).
+/// `update` only samples what has come into the window and drops what has left it.
+pub struct OrbitPath {
+    pub color: u32, // RGBA
+    /// Pixels, like `Points::width`
+    pub width: f32,
+    /// Seconds of trail drawn behind the clock
+    pub past: f64,
+    /// Seconds of path drawn ahead of the clock
+    pub future: f64,
+    /// Alpha at the old end of the trail, relative to `color`
+    pub fade: f32,
+    /// Alpha of the part ahead of the clock, relative to `color`
+    pub future_alpha: f32,
+    /// Largest turn (radians) between consecutive segments
+    pub max_angle: f64,
+    /// Shortest step (s), so sharp corners don't take forever
+    pub min_step: f64,
+    /// Transformation of the path, like `Points::tf`
+    pub tf: na::Matrix4<f64>,
+    trajectory: Box<dyn Trajectory>,
+    // (TDB seconds past J2000, position)
+    samples: VecDeque<(f64, na::Vector3<f64>)>,
+    now: f64,
+    anchor: f64,
+    // Each sample is written to two slots, `i` and `i + capacity`,
+    // so every run of samples is contiguous in the buffer whatever `head` is
+    head: usize,
+    capacity: usize,
+    vbo: u32,
+    vao: u32,
+}
+
+impl OrbitPath {
+    /// Path covering `past` seconds behind and `future` seconds ahead of the clock
+    pub fn new<T: Trajectory + 'static>(color: u32, trajectory: T, past: f64, future: f64) -> Self {
+        let mut vbo: u32 = 0;
+        let mut vao: u32 = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+        }
+        let mut s = Self {
+            color,
+            width: 2.0,
+            past,
+            future,
+            fade: 0.0,
+            future_alpha: 0.3,
+            max_angle: 2.0_f64.to_radians(),
+            min_step: 1.0,
+            tf: na::Matrix4::identity(),
+            trajectory: Box::new(trajectory),
+            samples: VecDeque::new(),
+            now: 0.0,
+            anchor: 0.0,
+            head: 0,
+            capacity: 0,
+            vbo,
+            vao,
+        };
+        s.allocate(256);
+        s
+    }
+
+    /// Sampled times and positions, oldest first
+    pub fn samples(&self) -> impl Iterator<Item = &(f64, na::Vector3<f64>)> {
+        self.samples.iter()
+    }
+
+    pub fn set_trajectory<T: Trajectory + 'static>(&mut self, trajectory: T) {
+        self.trajectory = Box::new(trajectory);
+        self.reset();
+    }
+
+    /// Resample from scratch on the next update, needed after changing `max_angle` or `min_step`
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.head = 0;
+    }
+
+    // New empty buffer with room for `capacity` samples
+    fn allocate(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (2 * capacity * std::mem::size_of::<Vertex>()) as isize,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+    }
+
+    // Upload sample `i` (counted from the front) to its two slots
+    fn write(&self, i: usize) {
+        let (t, p) = self.samples[i];
+        let v = Vertex {
+            position: [p.x as f32, p.y as f32, p.z as f32],
+            color: [0xFF; 4],
+            time: (t - self.anchor) as f32,
+        };
+        let slot = (self.head + i) % self.capacity;
+        let size = std::mem::size_of::<Vertex>();
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            for s in &[slot, slot + self.capacity] {
+                gl::BufferSubData(
+                    gl::ARRAY_BUFFER,
+                    (s * size) as isize,
+                    size as isize,
+                    &v as *const Vertex as *const _,
+                );
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
+    // Make room for one more sample, growing the buffer (and uploading everything) when full
+    fn reserve(&mut self) {
+        if self.samples.len() < self.capacity {
+            return;
+        }
+        self.allocate(self.capacity * 2);
+        self.head = 0;
+        for i in 0..self.samples.len() {
+            self.write(i);
+        }
+    }
+
+    fn push_back(&mut self, s: (f64, na::Vector3<f64>)) {
+        self.reserve();
+        self.samples.push_back(s);
+        self.write(self.samples.len() - 1);
+    }
+
+    fn push_front(&mut self, s: (f64, na::Vector3<f64>)) {
+        self.reserve();
+        self.samples.push_front(s);
+        self.head = (self.head + self.capacity - 1) % self.capacity;
+        self.write(0);
+    }
+
+    fn pop_front(&mut self) {
+        self.samples.pop_front();
+        self.head = (self.head + 1) % self.capacity;
+    }
+
+    // Next sample after `last` in direction `dir` (1 or -1), `prev` being the one before it.
+    // The step is doubled from the last one, then halved until the path turns by less than
+    // `max_angle`, which packs samples where curvature is high.
+    fn step(
+        &mut self,
+        prev: Option<(f64, na::Vector3<f64>)>,
+        last: (f64, na::Vector3<f64>),
+        dir: f64,
+    ) -> Option<(f64, na::Vector3<f64>)> {
+        let max_step = self.trajectory.max_step().max(self.min_step);
+        let mut dt = match prev {
+            Some(p) => ((last.0 - p.0).abs() * 2.0).min(max_step),
+            None => max_step / 16.0,
+        }
+        .max(self.min_step);
+        loop {
+            let t = last.0 + dir * dt;
+            let p = self.trajectory.position(t)?;
+            let turn = prev.map_or(0.0, |(_, q)| (last.1 - q).angle(&(p - last.1)));
+            // NaN when standing still, which is fine
+            if turn.is_nan() || turn <= self.max_angle || dt <= self.min_step {
+                return Some((t, p));
+            }
+            dt = (dt / 2.0).max(self.min_step);
+        }
+    }
+
+    fn extend_forward(&mut self, end: f64, budget: &mut usize) {
+        while *budget > 0 {
+            let n = self.samples.len();
+            let last = self.samples[n - 1];
+            if last.0 >= end {
+                break;
+            }
+            let prev = if n > 1 {
+                Some(self.samples[n - 2])
+            } else {
+                None
+            };
+            match self.step(prev, last, 1.0) {
+                Some(s) => self.push_back(s),
+                None => break,
+            }
+            *budget -= 1;
+        }
+    }
+
+    fn extend_backward(&mut self, start: f64, budget: &mut usize) {
+        while *budget > 0 {
+            let first = self.samples[0];
+            if first.0 <= start {
+                break;
+            }
+            let prev = self.samples.get(1).copied();
+            match self.step(prev, first, -1.0) {
+                Some(s) => self.push_front(s),
+                None => break,
+            }
+            *budget -= 1;
+        }
+    }
+}
+
+impl Drawable for OrbitPath {
+    fn draw(
+        &mut self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        _pass: Pass,
+        _s: na::Vector3<f32>,
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))] _t: &mut Vec<(
+            na::Vector3<f64>,
+            u32,
+            imgui::ImString,
+        )>,
+    ) -> Result<(), std::ffi::NulError> {
+        if self.samples.len() < 2 {
+            return Ok(());
+        }
+        let v = 0xFF as f32;
+        let color = na::Vector4::new(
+            (self.color >> 24) as f32 / v,
+            ((self.color >> 16) & 0xFF) as f32 / v,
+            ((self.color >> 8) & 0xFF) as f32 / v,
+            (self.color & 0xFF) as f32 / v,
+        );
+        let mut viewport = [0_i32; 4];
+        unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+        let projection = c.projection_matrix(g.aspect_ratio());
+        g.progs[5].use_gl();
+        g.progs[5].set_mat4("model", &c.relative(&(tf * self.tf)))?;
+        g.progs[5].set_mat4("view", &c.view_matrix())?;
+        g.progs[5].set_mat4("projection", &projection)?;
+        g.progs[5].set_depth(c)?;
+        g.progs[5].set_float("far_depth", -1.0)?;
+        g.progs[5].set_vec4("color", &color)?;
+        g.progs[5].set_vec4("viewport", &na::convert(na::Vector4::from(viewport)))?;
+        g.progs[5].set_float("width", self.width)?;
+        g.progs[5].set_int("style", 0)?;
+        g.progs[5].set_vec2("pattern", &na::Vector2::zeros())?;
+        g.progs[5].set_float("now", (self.now - self.anchor) as f32)?;
+        g.progs[5].set_float("past", self.past as f32)?;
+        g.progs[5].set_float("future", self.future as f32)?;
+        g.progs[5].set_float("fade", self.fade)?;
+        g.progs[5].set_float("future_alpha", self.future_alpha)?;
+        let size = std::mem::size_of::<Vertex>();
+        unsafe {
+            // Screen-space quads like `Points` lines, a segment from each sample to the next
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            super::drawable::line_attributes(size as i32, size as i32, self.head * size);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            let segments = self.samples.len() as i32 - 1;
+            gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, segments);
+            gl::Disable(gl::BLEND);
+            gl::BindVertexArray(0);
+        }
+        g.progs[5].unuse_gl();
+        Ok(())
+    }
+
+    fn update(&mut self, epoch: sputils::time::TDB) {
+        self.now = crate::clock::seconds_since_j2000(epoch);
+        let (start, end) = (self.now - self.past, self.now + self.future);
+        let overlaps = match (self.samples.front(), self.samples.back()) {
+            (Some(f), Some(b)) => f.0 <= end && b.0 >= start,
+            _ => false,
+        };
+        if !overlaps || (self.now - self.anchor).abs() > MAX_ANCHOR_AGE {
+            self.reset();
+            self.anchor = self.now;
+            match self.trajectory.position(self.now) {
+                Some(p) => self.push_back((self.now, p)),
+                None => return,
+            }
+        }
+        let mut budget = MAX_SAMPLES_PER_UPDATE;
+        self.extend_forward(end, &mut budget);
+        self.extend_backward(start, &mut budget);
+        // Keep one sample beyond each end, the shader cuts the line at the window
+        while self.samples.len() > 2 && self.samples[1].0 <= start {
+            self.pop_front();
+        }
+        while self.samples.len() > 2 && self.samples[self.samples.len() - 2].0 >= end {
+            self.samples.pop_back();
+        }
+    }
+
+    fn passes(&self) -> &'static [Pass] {
+        &[Pass::Transparent]
+    }
+
+    fn origin(&self) -> na::Vector3<f64> {
+        na::Vector3::new(self.tf.m14, self.tf.m24, self.tf.m34)
+    }
+}
+
+impl Drop for OrbitPath {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
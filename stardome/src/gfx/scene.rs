@@ -0,0 +1,284 @@
+// Retained scene graph
+// Nodes are frames with a transform relative to their parent, eg ISS -> Earth-fixed -> Earth -> SSB
+// Drawables attached to a node are drawn in its frame
+use super::drawable::{Drawable, Pass};
+
+/// Index of a node, only valid for the scene that created it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// Drawable in a scene, used to modify or remove it
+pub struct Handle<T> {
+    node: NodeId,
+    index: usize,
+    _t: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn node(&self) -> NodeId {
+        self.node
+    }
+}
+
+// Derives would require T: Clone
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+// Lets attached drawables be downcast
+pub(crate) trait AnyDrawable: Drawable {
+    fn as_any(&self) -> &dyn std::any::Any;
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
+    fn into_any(self: Box<Self>) -> Box<dyn std::any::Any>;
+}
+
+impl<T: Drawable + 'static> AnyDrawable for T {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn std::any::Any> {
+        self
+    }
+}
+
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    /// Transformation to the parent's frame, rotation and translation only
+    local: na::Matrix4<f64>,
+    world: na::Matrix4<f64>,
+    // Removed drawables leave a hole so handles stay valid
+    drawables: Vec<Option<Box<dyn AnyDrawable>>>,
+}
+
+/// Frames with parent-relative f64 transforms, and the drawables in them.
+///
+/// Drawables are added once and kept until removed, `StarDome::frame` draws them in `Pass` order.
+/// Transforms can be changed at any time, world transforms are worked out once per frame
+/// by `update`.
+pub struct Scene {
+    nodes: Vec<Node>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new("World")
+    }
+}
+
+impl Scene {
+    /// Graph with only the root node, named `root`
+    pub fn new(root: &str) -> Self {
+        Self {
+            nodes: vec![Node {
+                name: root.to_string(),
+                parent: None,
+                local: na::Matrix4::identity(),
+                world: na::Matrix4::identity(),
+                drawables: Vec::new(),
+            }],
+        }
+    }
+
+    /// Node all others descend from, its frame is world space
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn add(&mut self, parent: NodeId, name: &str, local: na::Matrix4<f64>) -> NodeId {
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent: Some(parent),
+            local,
+            world: self.nodes[parent.0].world * local,
+            drawables: Vec::new(),
+        });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// First node called `name`
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|n| n.name == name).map(NodeId)
+    }
+
+    pub fn name(&self, node: NodeId) -> &str {
+        &self.nodes[node.0].name
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node.0].parent
+    }
+
+    /// Move a node and everything below it to a new parent, keeping its local transform.
+    ///
+    /// Returns false (and does nothing) when that would make a cycle.
+    pub fn set_parent(&mut self, node: NodeId, parent: NodeId) -> bool {
+        if node == self.root() {
+            return false;
+        }
+        let mut p = Some(parent);
+        while let Some(n) = p {
+            if n == node {
+                return false;
+            }
+            p = self.nodes[n.0].parent;
+        }
+        self.nodes[node.0].parent = Some(parent);
+        true
+    }
+
+    pub fn local(&self, node: NodeId) -> &na::Matrix4<f64> {
+        &self.nodes[node.0].local
+    }
+
+    pub fn set_local(&mut self, node: NodeId, local: na::Matrix4<f64>) {
+        self.nodes[node.0].local = local;
+    }
+
+    /// Set only the translation of the local transform
+    pub fn set_position(&mut self, node: NodeId, position: &na::Vector3<f64>) {
+        let l = &mut self.nodes[node.0].local;
+        l.m14 = position.x;
+        l.m24 = position.y;
+        l.m34 = position.z;
+    }
+
+    /// Transformation to world space as of the last `update`
+    pub fn world(&self, node: NodeId) -> &na::Matrix4<f64> {
+        &self.nodes[node.0].world
+    }
+
+    /// Origin of a node in world space as of the last `update`
+    pub fn world_position(&self, node: NodeId) -> na::Vector3<f64> {
+        let w = &self.nodes[node.0].world;
+        na::Vector3::new(w.m14, w.m24, w.m34)
+    }
+
+    /// Recompute all world transforms, each node is visited once
+    pub fn update(&mut self) {
+        let mut done = vec![false; self.nodes.len()];
+        let mut chain = Vec::new();
+        for i in 0..self.nodes.len() {
+            // Walk up to the first node that is done, then back down
+            let mut n = i;
+            while !done[n] {
+                chain.push(n);
+                match self.nodes[n].parent {
+                    Some(p) => n = p.0,
+                    None => break,
+                }
+            }
+            while let Some(c) = chain.pop() {
+                self.nodes[c].world = match self.nodes[c].parent {
+                    Some(p) => self.nodes[p.0].world * self.nodes[c].local,
+                    None => self.nodes[c].local,
+                };
+                done[c] = true;
+            }
+        }
+    }
+
+    /// Draw `d` in the frame of `node` from now on
+    pub fn attach<T: Drawable + 'static>(&mut self, node: NodeId, d: T) -> Handle<T> {
+        let drawables = &mut self.nodes[node.0].drawables;
+        drawables.push(Some(Box::new(d)));
+        Handle {
+            node,
+            index: drawables.len() - 1,
+            _t: std::marker::PhantomData,
+        }
+    }
+
+    /// Attach to the root node
+    pub fn insert<T: Drawable + 'static>(&mut self, d: T) -> Handle<T> {
+        self.attach(self.root(), d)
+    }
+
+    pub fn get<T: Drawable + 'static>(&self, h: Handle<T>) -> &T {
+        self.nodes[h.node.0].drawables[h.index]
+            .as_ref()
+            .expect("Drawable was removed")
+            .as_any()
+            .downcast_ref()
+            .expect("Handle from another scene")
+    }
+
+    pub fn get_mut<T: Drawable + 'static>(&mut self, h: Handle<T>) -> &mut T {
+        self.nodes[h.node.0].drawables[h.index]
+            .as_mut()
+            .expect("Drawable was removed")
+            .as_any_mut()
+            .downcast_mut()
+            .expect("Handle from another scene")
+    }
+
+    /// Stop drawing it and give it back
+    pub fn remove<T: Drawable + 'static>(&mut self, h: Handle<T>) -> T {
+        let d = self.nodes[h.node.0].drawables[h.index]
+            .take()
+            .expect("Drawable was removed");
+        *d.into_any().downcast().expect("Handle from another scene")
+    }
+
+    /// Call `Drawable::update` on everything in the scene
+    pub(crate) fn update_drawables(&mut self, epoch: sputils::time::TDB) {
+        for d in self
+            .nodes
+            .iter_mut()
+            .flat_map(|n| n.drawables.iter_mut().flatten())
+        {
+            d.update(epoch);
+        }
+    }
+
+    /// Call `Drawable::update_view` on everything in the scene, after `update`
+    pub(crate) fn update_views(&mut self, g: &mut super::Graphics, c: &super::camera::Camera) {
+        for node in self.nodes.iter_mut() {
+            for d in node.drawables.iter_mut().flatten() {
+                d.update_view(g, c, &node.world);
+            }
+        }
+    }
+
+    /// Drawables in `pass` as (node, index), transparent ones sorted back to front
+    pub(crate) fn queue(&self, pass: Pass, eye: &na::Vector3<f64>) -> Vec<(usize, usize)> {
+        let mut q: Vec<(f64, (usize, usize))> = Vec::new();
+        for (n, node) in self.nodes.iter().enumerate() {
+            for (i, d) in node.drawables.iter().enumerate() {
+                let d = match d {
+                    Some(d) if d.passes().contains(&pass) => d,
+                    _ => continue,
+                };
+                let distance = if pass == Pass::Transparent {
+                    ((node.world * d.origin().push(1.0)).xyz() - eye).norm_squared()
+                } else {
+                    0.0
+                };
+                q.push((distance, (n, i)));
+            }
+        }
+        if pass == Pass::Transparent {
+            q.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        }
+        q.into_iter().map(|(_, k)| k).collect()
+    }
+
+    /// Drawable and the world transform of its node, from `queue`
+    pub(crate) fn entry_mut(
+        &mut self,
+        (n, i): (usize, usize),
+    ) -> Option<(&na::Matrix4<f64>, &mut (dyn AnyDrawable + 'static))> {
+        let node = &mut self.nodes[n];
+        let world = &node.world;
+        node.drawables[i].as_deref_mut().map(|d| (world, d))
+    }
+}
@@ -30,6 +30,17 @@ impl Shader {
         }
     }
 
+    /// Compile with `#define`s inserted after the `#version` line
+    pub fn with_defines(t: u32, s: &[u8], defines: &[&str]) -> Result<Self, std::io::Error> {
+        let split = s.iter().position(|&c| c == b'\n').map_or(s.len(), |i| i + 1);
+        let mut source = s[..split].to_vec();
+        for d in defines {
+            source.extend_from_slice(format!("#define {}\n", d).as_bytes());
+        }
+        source.extend_from_slice(&s[split..]);
+        Self::new(t, &source)
+    }
+
     pub fn vertex(s: &[u8]) -> Result<Self, std::io::Error> {
         Self::new(gl::VERTEX_SHADER, s)
     }
@@ -132,11 +143,29 @@ impl Program {
         Ok(())
     }
 
+    pub fn set_vec2(&self, name: &str, v: &na::Vector2<f32>) -> Result<(), std::ffi::NulError> {
+        let cstring = std::ffi::CString::new(name)?;
+        unsafe {
+            gl::Uniform2fv(
+                gl::GetUniformLocation(self.0, cstring.as_ptr()),
+                1,
+                v.as_slice().as_ptr(),
+            )
+        }
+        Ok(())
+    }
+
     pub fn set_float(&self, name: &str, f: f32) -> Result<(), std::ffi::NulError> {
         let cstring = std::ffi::CString::new(name)?;
         unsafe { gl::Uniform1f(gl::GetUniformLocation(self.0, cstring.as_ptr()), f) }
         Ok(())
     }
+
+    /// Uniforms for logarithmic depth
+    pub fn set_depth(&self, c: &super::camera::Camera) -> Result<(), std::ffi::NulError> {
+        self.set_float("log_depth", c.log_depth())?;
+        self.set_float("depth_near", c.get_clipping_planes().0)
+    }
 }
 
 impl Drop for Program {
@@ -0,0 +1,349 @@
+// Stars from a catalog file, drawn at infinity as sprites sized by magnitude
+// Directions are ICRS, moved by proper motion to the simulation epoch
+use super::drawable::{Drawable, Pass};
+use std::f64::consts::PI;
+use std::io::BufRead;
+
+// Proper motion is reapplied when the epoch moves this far (Julian years)
+const REPROPAGATE_YEARS: f64 = 0.1;
+const DEG: f64 = PI / 180.0;
+const MAS: f64 = DEG / 3_600_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    /// `hip_main.dat` from ESA/CDS (I/239), `|` separated, J1991.25
+    Hipparcos,
+    /// `tyc2.dat` from CDS (I/259), `|` separated mean positions at J2000
+    Tycho2,
+    /// Yale Bright Star Catalog 5th edition `catalog` from CDS (V/50), fixed width, J2000
+    BrightStar,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Star {
+    /// HIP number for Hipparcos and Tycho-2 (0 for Tycho-2 stars without one), HR number for
+    /// the Bright Star Catalog
+    pub id: u32,
+    /// Right ascension and declination (radians, ICRS) at `epoch`
+    pub ra: f64,
+    pub dec: f64,
+    /// Proper motion (radians per Julian year), the RA part multiplied by cos(dec)
+    pub pm_ra: f64,
+    pub pm_dec: f64,
+    /// Julian year of the position
+    pub epoch: f64,
+    /// Apparent visual magnitude
+    pub mag: f32,
+    /// B-V colour index, white when unknown
+    pub bv: Option<f32>,
+}
+
+impl Star {
+    /// Unit vector (ICRS) at Julian year `year`, moving linearly along the tangent plane
+    pub fn direction(&self, year: f64) -> na::Vector3<f64> {
+        let (sa, ca) = self.ra.sin_cos();
+        let (sd, cd) = self.dec.sin_cos();
+        let p = na::Vector3::new(cd * ca, cd * sa, sd);
+        let east = na::Vector3::new(-sa, ca, 0.0);
+        let north = na::Vector3::new(-sd * ca, -sd * sa, cd);
+        let dt = year - self.epoch;
+        (p + (east * self.pm_ra + north * self.pm_dec) * dt).normalize()
+    }
+
+    /// RGBA, approximate blackbody colour of the B-V index
+    pub fn color(&self) -> u32 {
+        let bv = match self.bv {
+            Some(bv) => bv.clamp(-0.4, 2.0) as f64,
+            None => return 0xFFFFFFFF,
+        };
+        // Ballesteros (2012)
+        let t = 4600.0 * (1.0 / (0.92 * bv + 1.7) + 1.0 / (0.92 * bv + 0.62)) / 100.0;
+        // Tanner Helland's fit to blackbody colours
+        let r = if t <= 66.0 {
+            255.0
+        } else {
+            329.698727446 * (t - 60.0).powf(-0.1332047592)
+        };
+        let g = if t <= 66.0 {
+            99.4708025861 * t.ln() - 161.1195681661
+        } else {
+            288.1221695283 * (t - 60.0).powf(-0.0755148492)
+        };
+        let b = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.5177312231 * (t - 10.0).ln() - 305.0447927307
+        };
+        let c = |x: f64| x.clamp(0.0, 255.0) as u32;
+        c(r) << 24 | c(g) << 16 | c(b) << 8 | 0xFF
+    }
+}
+
+/// Parse one catalog line, `None` for stars without a usable position or magnitude
+pub fn parse_star(line: &str, format: CatalogFormat) -> Option<Star> {
+    match format {
+        CatalogFormat::Hipparcos => {
+            let f: Vec<&str> = line.split('|').collect();
+            let num = |i: usize| f.get(i)?.trim().parse::<f64>().ok();
+            Some(Star {
+                id: f.get(1)?.trim().parse().ok()?,
+                ra: num(8)? * DEG,
+                dec: num(9)? * DEG,
+                pm_ra: num(12).unwrap_or(0.0) * MAS,
+                pm_dec: num(13).unwrap_or(0.0) * MAS,
+                epoch: 1991.25,
+                mag: num(5)? as f32,
+                bv: num(37).map(|x| x as f32),
+            })
+        }
+        CatalogFormat::Tycho2 => {
+            let f: Vec<&str> = line.split('|').collect();
+            let num = |i: usize| f.get(i)?.trim().parse::<f64>().ok();
+            // Convert Tycho BT/VT to Johnson V and B-V, ESA (1997) 1.3
+            let (mag, bv) = match (num(17), num(19)) {
+                (Some(bt), Some(vt)) => (vt - 0.090 * (bt - vt), Some(0.850 * (bt - vt))),
+                (None, Some(vt)) => (vt, None),
+                (Some(bt), None) => (bt, None),
+                (None, None) => return None,
+            };
+            Some(Star {
+                // HIP number then CCDM components
+                id: f
+                    .get(23)
+                    .and_then(|h| h.get(..6)?.trim().parse().ok())
+                    .unwrap_or(0),
+                ra: num(2)? * DEG,
+                dec: num(3)? * DEG,
+                pm_ra: num(4).unwrap_or(0.0) * MAS,
+                pm_dec: num(5).unwrap_or(0.0) * MAS,
+                epoch: 2000.0,
+                mag: mag as f32,
+                bv: bv.map(|x| x as f32),
+            })
+        }
+        CatalogFormat::BrightStar => {
+            // Columns are 1-based and inclusive, as in the catalog's ReadMe
+            let num = |a: usize, b: usize| line.get(a - 1..b)?.trim().parse::<f64>().ok();
+            let ra = (num(76, 77)? + num(78, 79)? / 60.0 + num(80, 83)? / 3600.0) * 15.0;
+            let dec = num(85, 86)? + num(87, 88)? / 60.0 + num(89, 90)? / 3600.0;
+            let sign = if line.get(83..84)? == "-" { -1.0 } else { 1.0 };
+            Some(Star {
+                id: line.get(0..4)?.trim().parse().ok()?,
+                ra: ra * DEG,
+                dec: sign * dec * DEG,
+                // arcsec/yr, RA taken as already multiplied by cos(dec)
+                pm_ra: num(149, 154).unwrap_or(0.0) * 1000.0 * MAS,
+                pm_dec: num(155, 160).unwrap_or(0.0) * 1000.0 * MAS,
+                epoch: 2000.0,
+                mag: num(103, 107)? as f32,
+                bv: num(110, 114).map(|x| x as f32),
+            })
+        }
+    }
+}
+
+#[repr(C)]
+struct Vertex {
+    direction: [f32; 3],
+    // RGBA bytes
+    color: [u8; 4],
+    magnitude: f32,
+}
+
+/// Catalog stars as sprites at infinity, drawn over the skybox and behind everything else.
+///
+/// A star's light (relative to one at `limiting_magnitude`) goes into its alpha until that
+/// saturates, then into its size.
+pub struct StarField {
+    /// Fainter stars aren't drawn
+    pub limiting_magnitude: f32,
+    /// Alpha of a star at the limiting magnitude
+    pub brightness: f32,
+    /// Radius (pixels) of stars up to full alpha
+    pub size: f32,
+    /// Largest radius (pixels)
+    pub max_size: f32,
+    // Brightest first, so everything down to the limit is a prefix
+    stars: Vec<Star>,
+    // Julian year the buffer was computed for
+    year: f64,
+    vbo: u32,
+    vao: u32,
+}
+
+impl StarField {
+    pub fn new(mut stars: Vec<Star>) -> Self {
+        stars.sort_by(|a, b| {
+            a.mag
+                .partial_cmp(&b.mag)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mut vbo: u32 = 0;
+        let mut vao: u32 = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+        }
+        let mut s = Self {
+            limiting_magnitude: 6.5,
+            brightness: 0.3,
+            size: 1.5,
+            max_size: 8.0,
+            stars,
+            year: 2000.0,
+            vbo,
+            vao,
+        };
+        s.update_stars();
+        s
+    }
+
+    pub fn open<P: AsRef<std::path::Path>>(
+        path: P,
+        format: CatalogFormat,
+    ) -> Result<Self, crate::BoxError> {
+        Self::load(
+            &mut std::io::BufReader::new(std::fs::File::open(path)?),
+            format,
+        )
+    }
+
+    /// One star per line, lines that can't be used are skipped
+    pub fn load<R: BufRead>(r: &mut R, format: CatalogFormat) -> Result<Self, crate::BoxError> {
+        let mut stars = Vec::new();
+        for line in r.lines() {
+            if let Some(s) = parse_star(&line?, format) {
+                stars.push(s);
+            }
+        }
+        Ok(Self::new(stars))
+    }
+
+    /// Brightest first
+    pub fn stars(&self) -> &[Star] {
+        &self.stars
+    }
+
+    // Stars at or brighter than the limiting magnitude
+    fn visible(&self) -> usize {
+        let limit = self.limiting_magnitude;
+        self.stars
+            .iter()
+            .position(|s| s.mag > limit)
+            .unwrap_or(self.stars.len())
+    }
+
+    fn update_stars(&mut self) {
+        let year = self.year;
+        let vertices: Vec<Vertex> = self
+            .stars
+            .iter()
+            .map(|s| {
+                let d = s.direction(year);
+                Vertex {
+                    direction: [d.x as f32, d.y as f32, d.z as f32],
+                    color: s.color().to_be_bytes(),
+                    magnitude: s.mag,
+                }
+            })
+            .collect();
+        let size = std::mem::size_of::<Vertex>() as i32;
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(vertices.as_slice()) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, size, std::ptr::null());
+            gl::VertexAttribPointer(1, 4, gl::UNSIGNED_BYTE, gl::TRUE, size, 12 as *const _);
+            gl::VertexAttribPointer(2, 1, gl::FLOAT, gl::FALSE, size, 16 as *const _);
+            for l in 0..3 {
+                gl::EnableVertexAttribArray(l);
+                gl::VertexAttribDivisor(l, 1);
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl Drawable for StarField {
+    fn draw(
+        &mut self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        tf: &na::Matrix4<f64>,
+        _pass: Pass,
+        _s: na::Vector3<f32>,
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))] _t: &mut Vec<(
+            na::Vector3<f64>,
+            u32,
+            imgui::ImString,
+        )>,
+    ) -> Result<(), std::ffi::NulError> {
+        let n = self.visible();
+        // No sky in diagrams, like the skybox
+        if n == 0 || c.is_orthographic() {
+            return Ok(());
+        }
+        let mut viewport = [0_i32; 4];
+        unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+        // Only the rotation of the node matters at infinity
+        let mut model: na::Matrix4<f32> = na::convert(*tf);
+        model.m14 = 0.0;
+        model.m24 = 0.0;
+        model.m34 = 0.0;
+        let projection = c.projection_matrix(g.aspect_ratio());
+        let far_depth = g.begin_sky();
+        g.progs[8].use_gl();
+        g.progs[8].set_mat4("model", &model)?;
+        g.progs[8].set_mat4("view", &c.rot_matrix())?;
+        g.progs[8].set_mat4("projection", &projection)?;
+        g.progs[8].set_float("far_depth", far_depth)?;
+        g.progs[8].set_vec4("viewport", &na::convert(na::Vector4::from(viewport)))?;
+        g.progs[8].set_float("limit", self.limiting_magnitude)?;
+        g.progs[8].set_float("brightness", self.brightness)?;
+        g.progs[8].set_float("size", self.size)?;
+        g.progs[8].set_float("max_size", self.max_size)?;
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+            gl::BindVertexArray(self.vao);
+            gl::Enable(gl::BLEND);
+            // Overlapping stars add up
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+            gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, n as i32);
+            gl::Disable(gl::BLEND);
+            gl::BindVertexArray(0);
+            gl::DepthMask(gl::TRUE);
+        }
+        g.progs[8].unuse_gl();
+        g.end_sky();
+        Ok(())
+    }
+
+    fn update(&mut self, epoch: sputils::time::TDB) {
+        let year = 2000.0 + crate::clock::seconds_since_j2000(epoch) / (365.25 * 86400.0);
+        if (year - self.year).abs() > REPROPAGATE_YEARS {
+            self.year = year;
+            self.update_stars();
+        }
+    }
+
+    fn passes(&self) -> &'static [Pass] {
+        &[Pass::Sky]
+    }
+}
+
+impl Drop for StarField {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
@@ -0,0 +1,719 @@
+// Cube-sphere planet surface split into a quadtree of chunks, refined near the camera
+// Imagery and elevation are streamed from tile directories, chunks are built on a worker thread
+// Tiles use the geodetic (equirectangular) profile: zoom z has 2^(z+1) by 2^z tiles
+use super::mesh::Mesh;
+use super::texture::Texture;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::FRAC_PI_4;
+use std::path::PathBuf;
+
+// Quads along a chunk's edge
+const GRID: usize = 32;
+// Pixels along a chunk texture's edge
+const TEXELS: u32 = 256;
+// Decoded tiles the worker keeps between chunks
+const CACHE_TILES: usize = 64;
+// Requested but not loaded, so chunks near the camera don't wait behind a long queue
+const MAX_IN_FLIGHT: usize = 16;
+// Loaded chunks turned into GL objects per frame
+const UPLOADS_PER_FRAME: usize = 4;
+// Children are dropped this much further away than they are made
+const HYSTERESIS: f64 = 1.5;
+// Drawn for missing imagery
+const GREY: [f64; 4] = [128.0, 128.0, 128.0, 255.0];
+
+// Normal, right and up of each face, right x up = normal so chunks face outwards
+const FACES: [[[f64; 3]; 3]; 6] = [
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    [[-1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 1.0]],
+    [[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+    [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+    [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]],
+    [[0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+];
+
+/// Whether tile rows are counted from the top (XYZ) or the bottom (TMS)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileLayout {
+    Xyz,
+    Tms,
+}
+
+/// Directory of `{zoom}/{x}/{y}.{extension}` tiles in the geodetic profile.
+///
+/// Missing tiles are filled in from lower zoom levels, so detail only needs to exist where it
+/// is wanted, such as around a ground station.
+#[derive(Debug, Clone)]
+pub struct TileSource {
+    pub dir: PathBuf,
+    pub extension: String,
+    pub layout: TileLayout,
+    /// Deepest zoom level there are tiles for, anywhere
+    pub max_zoom: u32,
+}
+
+impl TileSource {
+    /// PNG tiles in XYZ layout down to zoom 18
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            extension: "png".to_owned(),
+            layout: TileLayout::Xyz,
+            max_zoom: 18,
+        }
+    }
+
+    fn path(&self, z: u32, x: u32, y: u32) -> PathBuf {
+        let y = match self.layout {
+            TileLayout::Xyz => y,
+            TileLayout::Tms => (1 << z) - 1 - y,
+        };
+        self.dir
+            .join(z.to_string())
+            .join(x.to_string())
+            .join(format!("{}.{}", y, self.extension))
+    }
+}
+
+/// Heights from greyscale tiles
+#[derive(Debug, Clone)]
+pub struct Elevation {
+    pub tiles: TileSource,
+    /// Heights (units, from the ellipsoid) of black and white
+    pub min: f64,
+    pub max: f64,
+    /// Multiplies the heights, only for chunks loaded after it changes
+    pub exaggeration: f64,
+}
+
+impl Elevation {
+    /// Heights in km
+    pub fn new(tiles: TileSource, min: f64, max: f64) -> Self {
+        Self {
+            tiles,
+            min: min / crate::KM_PER_UNIT,
+            max: max / crate::KM_PER_UNIT,
+            exaggeration: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    face: u8,
+    level: u8,
+    x: u32,
+    y: u32,
+}
+
+impl Key {
+    fn root(face: usize) -> Self {
+        Self {
+            face: face as u8,
+            level: 0,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    fn children(self) -> [Key; 4] {
+        let child = |i: u32, j: u32| Key {
+            face: self.face,
+            level: self.level + 1,
+            x: self.x * 2 + i,
+            y: self.y * 2 + j,
+        };
+        [child(0, 0), child(1, 0), child(0, 1), child(1, 1)]
+    }
+
+    // Corner and size on the face, which spans -1 to 1
+    fn bounds(self) -> (f64, f64, f64) {
+        let size = 2.0 / (1_u64 << self.level) as f64;
+        (
+            -1.0 + self.x as f64 * size,
+            -1.0 + self.y as f64 * size,
+            size,
+        )
+    }
+}
+
+// Unit vector through a point on a cube face
+fn cube_direction(face: u8, u: f64, v: f64) -> na::Vector3<f64> {
+    let [n, r, up] = FACES[face as usize];
+    // Equal angles rather than equal lengths on the cube, for more even chunks
+    let (a, b) = ((u * FRAC_PI_4).tan(), (v * FRAC_PI_4).tan());
+    (na::Vector3::from(n) + na::Vector3::from(r) * a + na::Vector3::from(up) * b).normalize()
+}
+
+// Tile holding a point (degrees), and where in it (0 to 1 from the top left)
+fn locate(lat: f64, lon: f64, z: u32) -> (u32, u32, f64, f64) {
+    let cols = (2_u64 << z) as f64;
+    let rows = (1_u64 << z) as f64;
+    let fx = ((lon + 180.0) / 360.0 * cols).max(0.0).min(cols - 1e-9);
+    let fy = ((90.0 - lat) / 180.0 * rows).max(0.0).min(rows - 1e-9);
+    (fx as u32, fy as u32, fx.fract(), fy.fract())
+}
+
+// Bilinear within one image, clamped at its edges
+fn bilinear<F: Fn(u32, u32) -> [f64; 4]>(w: u32, h: u32, px: f64, py: f64, get: F) -> [f64; 4] {
+    let x = px.max(0.0).min((w - 1) as f64);
+    let y = py.max(0.0).min((h - 1) as f64);
+    let (x0, y0) = (x as u32, y as u32);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let (a, b, c, d) = (get(x0, y0), get(x1, y0), get(x0, y1), get(x1, y1));
+    let mut out = [0.0; 4];
+    for (i, o) in out.iter_mut().enumerate() {
+        *o = (a[i] * (1.0 - fx) + b[i] * fx) * (1.0 - fy) + (c[i] * (1.0 - fx) + d[i] * fx) * fy;
+    }
+    out
+}
+
+// Everything a chunk needs from the worker
+struct ChunkData {
+    key: Key,
+    origin: na::Vector3<f64>,
+    radius: f64,
+    // In the format of `Mesh::load_gl`, relative to `origin`
+    vertices: Vec<f32>,
+    indices: Vec<u32>,
+    image: image::RgbaImage,
+}
+
+// Zoom, column and row
+type TileKey = (u32, u32, u32);
+type HeightTile = image::ImageBuffer<image::Luma<u16>, Vec<u16>>;
+
+// Loads tiles and builds chunks, on the worker thread
+struct Builder {
+    imagery: TileSource,
+    elevation: Option<Elevation>,
+    // Equatorial and polar (units)
+    radii: (f64, f64),
+    // `None` for tiles that don't exist
+    colors: HashMap<TileKey, Option<image::RgbaImage>>,
+    heights: HashMap<TileKey, Option<HeightTile>>,
+}
+
+impl Builder {
+    // RGBA 0-255 at a point (degrees), from the deepest tile there is at or above zoom `z`
+    fn color(&mut self, lat: f64, lon: f64, z: u32) -> [f64; 4] {
+        for z in (0..=z).rev() {
+            let (x, y, fx, fy) = locate(lat, lon, z);
+            let imagery = &self.imagery;
+            let tile = self.colors.entry((z, x, y)).or_insert_with(|| {
+                image::open(imagery.path(z, x, y))
+                    .ok()
+                    .map(|i| i.to_rgba8())
+            });
+            if let Some(img) = tile {
+                let (w, h) = img.dimensions();
+                return bilinear(w, h, fx * w as f64 - 0.5, fy * h as f64 - 0.5, |x, y| {
+                    let p = img.get_pixel(x, y).0;
+                    [p[0] as f64, p[1] as f64, p[2] as f64, p[3] as f64]
+                });
+            }
+        }
+        GREY
+    }
+
+    // Units above the ellipsoid, 0 where there are no tiles
+    fn height(&mut self, lat: f64, lon: f64, z: u32) -> f64 {
+        let e = match &self.elevation {
+            Some(e) => e,
+            None => return 0.0,
+        };
+        for z in (0..=z.min(e.tiles.max_zoom)).rev() {
+            let (x, y, fx, fy) = locate(lat, lon, z);
+            let tile = self.heights.entry((z, x, y)).or_insert_with(|| {
+                image::open(e.tiles.path(z, x, y))
+                    .ok()
+                    .map(|i| i.to_luma16())
+            });
+            if let Some(img) = tile {
+                let (w, h) = img.dimensions();
+                let v = bilinear(w, h, fx * w as f64 - 0.5, fy * h as f64 - 0.5, |x, y| {
+                    [img.get_pixel(x, y).0[0] as f64 / 65535.0; 4]
+                })[0];
+                return (e.min + (e.max - e.min) * v) * e.exaggeration;
+            }
+        }
+        0.0
+    }
+
+    fn build(&mut self, key: Key) -> ChunkData {
+        if self.colors.len() + self.heights.len() > CACHE_TILES {
+            self.colors.clear();
+            self.heights.clear();
+        }
+        let (u0, v0, size) = key.bounds();
+        let (a, b) = self.radii;
+        // Tiles a little finer than the chunk's texels
+        let zoom = (key.level as u32 + 1).min(self.imagery.max_zoom);
+        // Point on the ellipsoid, its geodetic normal, latitude and longitude (degrees)
+        let surface = |u: f64, v: f64| {
+            let d = cube_direction(key.face, u, v);
+            let p = na::Vector3::new(a * d.x, a * d.y, b * d.z);
+            let n = na::Vector3::new(d.x / a, d.y / a, d.z / b).normalize();
+            (p, n, n.z.asin().to_degrees(), n.y.atan2(n.x).to_degrees())
+        };
+
+        // Grid with a ring around it, so normals at the edges match the neighbours
+        let m = GRID + 3;
+        let mut points = Vec::with_capacity(m * m);
+        let mut ups = Vec::with_capacity(m * m);
+        for j in 0..m {
+            for i in 0..m {
+                let u = u0 + (i as f64 - 1.0) / GRID as f64 * size;
+                let v = v0 + (j as f64 - 1.0) / GRID as f64 * size;
+                let (p, n, lat, lon) = surface(u, v);
+                points.push(p + n * self.height(lat, lon, zoom));
+                ups.push(n);
+            }
+        }
+        let origin = points[(GRID / 2 + 1) * m + GRID / 2 + 1];
+
+        let texel = |i: usize| {
+            ((0.5 + i as f64 / GRID as f64 * (TEXELS - 1) as f64) / TEXELS as f64) as f32
+        };
+        let mut vertices = Vec::with_capacity(11 * ((GRID + 1) * (GRID + 5)));
+        let mut push = |p: na::Vector3<f64>, n: na::Vector3<f64>, up: na::Vector3<f64>, i, j| {
+            let east = na::Vector3::new(-up.y, up.x, 0.0);
+            let east = if east.norm() > 1e-9 {
+                east.normalize()
+            } else {
+                na::Vector3::y()
+            };
+            let p = p - origin;
+            vertices.extend_from_slice(&[
+                p.x as f32,
+                p.y as f32,
+                p.z as f32,
+                n.x as f32,
+                n.y as f32,
+                n.z as f32,
+                texel(i),
+                texel(j),
+                east.x as f32,
+                east.y as f32,
+                east.z as f32,
+            ]);
+        };
+        for j in 0..=GRID {
+            for i in 0..=GRID {
+                let k = (j + 1) * m + i + 1;
+                let n = (points[k + 1] - points[k - 1])
+                    .cross(&(points[k + m] - points[k - m]))
+                    .normalize();
+                push(points[k], n, ups[k], i, j);
+            }
+        }
+        let mut indices = Vec::with_capacity(6 * GRID * GRID + 48 * GRID);
+        let row = GRID as u32 + 1;
+        for j in 0..GRID as u32 {
+            for i in 0..GRID as u32 {
+                let k = j * row + i;
+                indices.extend_from_slice(&[k, k + 1, k + row, k + 1, k + row + 1, k + row]);
+            }
+        }
+
+        // Skirts hang down from the edges to hide cracks next to coarser chunks
+        let skirt = a * size * 0.05;
+        let mut radius: f64 = 0.0;
+        let mut next = row * row;
+        for edge in 0..4 {
+            let (mut first, mut last) = (None, 0);
+            for s in 0..=GRID {
+                let (i, j) = match edge {
+                    0 => (s, 0),
+                    1 => (GRID, s),
+                    2 => (GRID - s, GRID),
+                    _ => (0, GRID - s),
+                };
+                let k = (j + 1) * m + i + 1;
+                let n = (points[k + 1] - points[k - 1])
+                    .cross(&(points[k + m] - points[k - m]))
+                    .normalize();
+                let low = points[k] - ups[k] * skirt;
+                radius = radius.max((low - origin).norm());
+                push(low, n, ups[k], i, j);
+                let top = (j * (GRID + 1) + i) as u32;
+                if let Some(prev_top) = first {
+                    // Both windings, the skirt is seen from either side
+                    indices.extend_from_slice(&[prev_top, top, next, prev_top, next, last]);
+                    indices.extend_from_slice(&[prev_top, next, top, prev_top, last, next]);
+                }
+                first = Some(top);
+                last = next;
+                next += 1;
+            }
+        }
+        for k in 0..GRID + 1 {
+            for l in 0..GRID + 1 {
+                radius = radius.max((points[(k + 1) * m + l + 1] - origin).norm());
+            }
+        }
+
+        let mut image = image::RgbaImage::new(TEXELS, TEXELS);
+        let last = (TEXELS - 1) as f64;
+        for r in 0..TEXELS {
+            for col in 0..TEXELS {
+                // Row 0 is the top, the texture is flipped when loaded
+                let u = u0 + col as f64 / last * size;
+                let v = v0 + (last - r as f64) / last * size;
+                let (_, _, lat, lon) = surface(u, v);
+                let c = self.color(lat, lon, zoom);
+                image.put_pixel(
+                    col,
+                    r,
+                    image::Rgba([c[0] as u8, c[1] as u8, c[2] as u8, 255]),
+                );
+            }
+        }
+
+        ChunkData {
+            key,
+            origin,
+            radius,
+            vertices,
+            indices,
+            image,
+        }
+    }
+}
+
+#[cfg(not(target_os = "emscripten"))]
+struct Loader {
+    requests: std::sync::mpsc::Sender<Key>,
+    results: std::sync::mpsc::Receiver<ChunkData>,
+}
+
+#[cfg(not(target_os = "emscripten"))]
+impl Loader {
+    fn new(mut builder: Builder) -> Self {
+        let (requests, rx) = std::sync::mpsc::channel();
+        let (tx, results) = std::sync::mpsc::channel();
+        // Stops when the terrain is dropped
+        std::thread::spawn(move || {
+            for key in rx {
+                if tx.send(builder.build(key)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, results }
+    }
+
+    fn request(&mut self, key: Key) {
+        let _ = self.requests.send(key);
+    }
+
+    fn poll(&mut self) -> Option<ChunkData> {
+        self.results.try_recv().ok()
+    }
+}
+
+// No threads, a chunk is built every poll
+#[cfg(target_os = "emscripten")]
+struct Loader {
+    builder: Builder,
+    queue: std::collections::VecDeque<Key>,
+}
+
+#[cfg(target_os = "emscripten")]
+impl Loader {
+    fn new(builder: Builder) -> Self {
+        Self {
+            builder,
+            queue: std::collections::VecDeque::new(),
+        }
+    }
+
+    fn request(&mut self, key: Key) {
+        self.queue.push_back(key);
+    }
+
+    fn poll(&mut self) -> Option<ChunkData> {
+        let key = self.queue.pop_front()?;
+        Some(self.builder.build(key))
+    }
+}
+
+struct Chunk {
+    key: Key,
+    // Middle of the chunk in the planet's frame (units)
+    origin: na::Vector3<f64>,
+    // Bounding sphere about `origin`
+    radius: f64,
+    mesh: Mesh,
+    texture: Texture,
+    children: Option<Box<[Chunk; 4]>>,
+}
+
+impl Chunk {
+    fn upload(d: ChunkData) -> Self {
+        Self {
+            key: d.key,
+            origin: d.origin,
+            radius: d.radius,
+            mesh: unsafe { Mesh::load_gl(&d.vertices, &d.indices) },
+            texture: Texture::from_image(image::DynamicImage::ImageRgba8(d.image)),
+            children: None,
+        }
+    }
+}
+
+// What refining the tree needs besides the chunk
+struct Streams<'a> {
+    ready: &'a mut HashMap<Key, Chunk>,
+    pending: &'a mut HashSet<Key>,
+    loader: &'a mut Loader,
+    // Chunks the tree could use this frame, loaded ones that aren't are dropped
+    wanted: HashSet<Key>,
+    cull: Cull<'a>,
+    detail: f64,
+    max_level: u8,
+}
+
+impl Streams<'_> {
+    fn request(&mut self, key: Key) {
+        self.wanted.insert(key);
+        if !self.ready.contains_key(&key)
+            && !self.pending.contains(&key)
+            && self.pending.len() < MAX_IN_FLIGHT
+        {
+            self.pending.insert(key);
+            self.loader.request(key);
+        }
+    }
+
+    // Split chunks close to the eye once their children are loaded, merge distant ones.
+    // Chunks out of view keep their children but don't load any
+    fn refine(&mut self, chunk: &mut Chunk) {
+        let distance = (self.cull.eye - chunk.origin).norm() - chunk.radius;
+        let reach = self.detail * chunk.radius;
+        if distance > reach * HYSTERESIS {
+            chunk.children = None;
+            return;
+        }
+        if !self.cull.visible(chunk) {
+            return;
+        }
+        if distance < reach && chunk.key.level < self.max_level && chunk.children.is_none() {
+            let keys = chunk.key.children();
+            for k in keys.iter() {
+                self.request(*k);
+            }
+            if keys.iter().all(|k| self.ready.contains_key(k)) {
+                let [a, b, c, d] = keys;
+                let mut take = |k: Key| self.ready.remove(&k).expect("child is loaded");
+                chunk.children = Some(Box::new([take(a), take(b), take(c), take(d)]));
+            }
+        }
+        if let Some(children) = &mut chunk.children {
+            for ch in children.iter_mut() {
+                self.refine(ch);
+            }
+        }
+    }
+}
+
+// Frustum and horizon culling
+struct Cull<'a> {
+    world: &'a na::Matrix4<f64>,
+    camera: na::Vector3<f64>,
+    // Camera in the planet's frame
+    eye: na::Vector3<f64>,
+    // Radius (units) of the lowest ground
+    floor: f64,
+    // Sides of the view, camera relative
+    planes: Vec<na::Vector4<f32>>,
+}
+
+impl<'a> Cull<'a> {
+    fn new(
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        world: &'a na::Matrix4<f64>,
+        floor: f64,
+    ) -> Self {
+        let inverse = world.try_inverse().unwrap_or_else(na::Matrix4::identity);
+        let planes = match c.projection {
+            // Only what is behind the camera is out of a dome of up to a hemisphere
+            super::camera::Projection::Fisheye { fov } if fov <= 180.0 => {
+                let forward = c.orientation * -na::Vector3::z();
+                vec![forward.push(0.0)]
+            }
+            super::camera::Projection::Fisheye { .. } => Vec::new(),
+            _ => {
+                let m = c.matrix(g.aspect_ratio());
+                let row = |i: usize| m.row(i).transpose();
+                vec![
+                    row(3) + row(0),
+                    row(3) - row(0),
+                    row(3) + row(1),
+                    row(3) - row(1),
+                ]
+            }
+        };
+        Self {
+            world,
+            camera: c.position,
+            eye: (inverse * c.position.push(1.0)).xyz(),
+            floor,
+            planes,
+        }
+    }
+
+    fn visible(&self, chunk: &Chunk) -> bool {
+        // Entirely under the plane of the horizon of the lowest ground, approximate for
+        // mountains seen over the horizon
+        let d = self.eye.norm();
+        if d > self.floor && chunk.origin.dot(&self.eye) / d + chunk.radius < self.floor.powi(2) / d
+        {
+            return false;
+        }
+        let p: na::Vector3<f32> =
+            na::convert((self.world * chunk.origin.push(1.0)).xyz() - self.camera);
+        let r = chunk.radius as f32;
+        self.planes
+            .iter()
+            .all(|pl| pl.xyz().dot(&p) + pl.w >= -r * pl.xyz().norm())
+    }
+
+    // Deepest chunks that are loaded and in view
+    fn collect<'b>(&self, chunk: &'b Chunk, out: &mut Vec<&'b Chunk>) {
+        if !self.visible(chunk) {
+            return;
+        }
+        match &chunk.children {
+            Some(children) => {
+                for ch in children.iter() {
+                    self.collect(ch, out);
+                }
+            }
+            None => out.push(chunk),
+        }
+    }
+}
+
+/// Cube-sphere surface for a `Planet`, split into a quadtree of chunks that is refined near
+/// the camera and streamed from tiles.
+///
+/// Chunks are textured from `TileSource` imagery and displaced by an optional `Elevation`,
+/// a chunk is drawn until all four of its children have loaded.
+pub struct Terrain {
+    /// Chunks split when the camera is closer than this many of their radii
+    pub detail: f64,
+    /// Deepest level of the quadtree, each level halves the size of the chunks
+    pub max_level: u8,
+    // Radius (units) of the lowest ground
+    floor: f64,
+    roots: Vec<Option<Chunk>>,
+    // Loaded, waiting for their siblings
+    ready: HashMap<Key, Chunk>,
+    pending: HashSet<Key>,
+    loader: Loader,
+}
+
+impl Terrain {
+    /// Radii in units, like `Planet`
+    pub fn new(
+        imagery: TileSource,
+        elevation: Option<Elevation>,
+        r_equatorial: f64,
+        r_polar: f64,
+    ) -> Self {
+        let floor = r_polar
+            + elevation
+                .as_ref()
+                .map_or(0.0, |e| (e.min * e.exaggeration).min(0.0));
+        let max_level = imagery.max_zoom.min(24) as u8;
+        let builder = Builder {
+            imagery,
+            elevation,
+            radii: (r_equatorial, r_polar),
+            colors: HashMap::new(),
+            heights: HashMap::new(),
+        };
+        Self {
+            detail: 2.0,
+            max_level,
+            floor,
+            roots: (0..6).map(|_| None).collect(),
+            ready: HashMap::new(),
+            pending: HashSet::new(),
+            loader: Loader::new(builder),
+        }
+    }
+
+    /// Uploads loaded chunks and refines the tree for the main camera, once per frame.
+    ///
+    /// `world` is the planet's transform without scaling
+    pub(crate) fn update(
+        &mut self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        world: &na::Matrix4<f64>,
+    ) {
+        for _ in 0..UPLOADS_PER_FRAME {
+            match self.loader.poll() {
+                Some(d) => {
+                    self.pending.remove(&d.key);
+                    self.ready.insert(d.key, Chunk::upload(d));
+                }
+                None => break,
+            }
+        }
+
+        let mut streams = Streams {
+            ready: &mut self.ready,
+            pending: &mut self.pending,
+            loader: &mut self.loader,
+            wanted: HashSet::new(),
+            cull: Cull::new(g, c, world, self.floor),
+            detail: self.detail,
+            max_level: self.max_level,
+        };
+        for (face, root) in self.roots.iter_mut().enumerate() {
+            if root.is_none() {
+                let key = Key::root(face);
+                streams.request(key);
+                *root = streams.ready.remove(&key);
+            }
+            if let Some(root) = root {
+                streams.refine(root);
+            }
+        }
+        let wanted = streams.wanted;
+        self.ready.retain(|k, _| wanted.contains(k));
+    }
+
+    /// Draw with `progs[0]` set up for the planet, `world` is its transform without scaling
+    pub(crate) fn draw(
+        &self,
+        g: &mut super::Graphics,
+        c: &super::camera::Camera,
+        world: &na::Matrix4<f64>,
+    ) -> Result<(), std::ffi::NulError> {
+        let cull = Cull::new(g, c, world, self.floor);
+        let mut visible = Vec::new();
+        for root in self.roots.iter().flatten() {
+            cull.collect(root, &mut visible);
+        }
+
+        // Chunks have their own texture coordinates, the planet's other maps don't line up
+        g.progs[0].set_float("night_intensity", 0.0)?;
+        g.progs[0].set_float("cloud_shadow", 0.0)?;
+        g.progs[0].set_int("has_normal_map", 0)?;
+        g.progs[0].set_int("has_specular_map", 0)?;
+        g.progs[0].set_vec2("height_range", &na::Vector2::zeros())?;
+        for ch in visible {
+            let model = world * na::Matrix4::new_translation(&ch.origin);
+            g.progs[0].set_mat4("model", &c.relative(&model))?;
+            ch.texture.bind(0);
+            ch.mesh.draw();
+        }
+        Ok(())
+    }
+}
@@ -30,7 +30,7 @@ impl Texture {
         Ok(Self::from_image(i))
     }
 
-    fn from_image(img: image::DynamicImage) -> Self {
+    pub(crate) fn from_image(img: image::DynamicImage) -> Self {
         let i = img.flipv();
         use image::DynamicImage::*;
         use std::ffi::c_void;
@@ -82,6 +82,32 @@ impl Texture {
         }
     }
 
+    /// Half float RGB without mipmaps, rows from the bottom, for lookup tables
+    pub(crate) fn from_rgb_f32(width: usize, height: usize, rgb: &[f32]) -> Self {
+        unsafe {
+            let mut id: u32 = 0;
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB16F as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                rgb.as_ptr() as *const std::ffi::c_void,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            Self(id)
+        }
+    }
+
     pub fn bind(&self, n: u32) {
         let mut value: i32 = 0;
         unsafe {
@@ -96,7 +122,11 @@ impl Texture {
     }
 }
 
-// TODO impl Drop
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.0) }
+    }
+}
 
 #[repr(transparent)]
 pub struct Cubemap(u32);
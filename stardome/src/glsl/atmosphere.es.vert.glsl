@@ -8,8 +8,17 @@ out vec3 FragPos;
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
+// 1 / log2(far / near + 1), 0 when logarithmic depth is off
+uniform float log_depth;
+uniform float depth_near;
+
+out float flogz;
 
 void main() {
     gl_Position = projection * view * model * vec4(aPos, 1.0);
+    flogz = 1.0 + gl_Position.w / depth_near;
+    if (log_depth > 0.0) {
+        gl_Position.z = (log2(max(1e-6, flogz)) * log_depth * 2.0 - 1.0) * gl_Position.w;
+    }
     FragPos = vec3(model * vec4(aPos, 1.0));
 }
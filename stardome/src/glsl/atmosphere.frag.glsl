@@ -4,8 +4,12 @@ out vec4 FragColor;
 in vec4 gl_FragCoord;
 in vec3 FragPos;
 
-// Based on https://www.scratchapixel.com/lessons/procedural-generation-virtual-worlds/simulating-sky/simulating-colors-of-the-sky
+// Single scattering marched along the view ray, with the sunlight reaching each sample and
+// the light scattered more than once looked up from tables made in gfx/atmosphere.rs
+// Based on Hillaire, "A Scalable and Production Ready Sky and Atmosphere Rendering Technique"
 
+in float flogz;
+uniform float log_depth;
 // Direction of sun (not position)
 uniform vec3 sun_dir;
 // Position relative to world
@@ -18,23 +22,25 @@ uniform float Re;
 uniform float Ra;
 // Rayleigh Scale height
 uniform float Hr;
-// Rayleigh attenuation coefficients
+// Rayleigh scattering coefficients
 uniform vec3 betaR;
 // Mie Scale height
-//uniform float Hm;
-// Mie attenuation coefficients
-//uniform vec3 betaM;
+uniform float Hm;
+// Mie scattering coefficients
+uniform vec3 betaM;
 // Mie asymmetry factor
-//uniform float g;
+uniform float g;
 // Sun intensity factor
 uniform float intensity;
+// Transmittance to the top of the atmosphere by radius and angle from the zenith
+uniform sampler2D transmittance_lut;
+// Light scattered more than once by radius and angle of the sun from the zenith
+uniform sampler2D multiscattering_lut;
 
 #define PI radians(180.0)
 #define INFINITY uintBitsToFloat(0x7F800000u)
 
-//#define MIE
-#define SAMPLES (1u)
-#define SAMPLES_LIGHT (1u)
+#define SAMPLES (32u)
 #define MIE_EXTINCTION_MUL (1.1)
 
 // Dir must be normalized
@@ -52,74 +58,67 @@ bool raySphereIntersect(const in vec3 orig, const in vec3 dir, const in float ra
 	return true;
 }
 
+// 0 and 1 are the centres of the first and last texels
+vec2 lutCoord(const in vec2 x, const in vec2 size) {
+    return (x * (size - 1.0) + 0.5) / size;
+}
+
+// Sunlight reaching radius r with the sun at cosine mu from the zenith
+vec3 transmittance(const in float r, const in float mu) {
+    // Behind the planet
+    if (mu < 0.0 && r * r * (mu * mu - 1.0) + Re * Re >= 0.0) return vec3(0.0);
+    float H = sqrt(Ra * Ra - Re * Re);
+    float rho = sqrt(max(r * r - Re * Re, 0.0));
+    float d = max(-r * mu + sqrt(max(r * r * (mu * mu - 1.0) + Ra * Ra, 0.0)), 0.0);
+    float dMin = Ra - r;
+    float dMax = rho + H;
+    vec2 x = vec2((d - dMin) / (dMax - dMin), rho / H);
+    return texture(transmittance_lut, lutCoord(x, vec2(textureSize(transmittance_lut, 0)))).rgb;
+}
+
+// Per unit of scattering coefficient
+vec3 multipleScattering(const in float r, const in float mu) {
+    vec2 x = vec2(mu * 0.5 + 0.5, clamp((r - Re) / (Ra - Re), 0.0, 1.0));
+    return texture(multiscattering_lut, lutCoord(x, vec2(textureSize(multiscattering_lut, 0)))).rgb;
+}
+
+// Light scattered towards the camera, and the opacity of the air in alpha
 vec4 computeIncidentLight(const in vec3 orig, const in vec3 dir, in float tmin, in float tmax, const in vec3 sunDirection) {
     float t0, t1;
     if (!raySphereIntersect(orig, dir, Ra, t0, t1) || t1 < 0.0) discard;
     if (t0 > tmin && t0 > 0.0) tmin = t0;
     if (t1 < tmax) tmax = t1;
     float segmentLength = (tmax - tmin) / float(SAMPLES);
-    float tCurrent = tmin;
-    vec3 sumR = vec3(0.0); // rayleigh contribution
-    float opticalDepthR = 0.0;
     float mu = dot(dir, sunDirection); // mu in the paper which is the cosine of the angle between the sun direction and the ray direction
     float phaseR = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
-    #ifdef MIE
-    vec3 sumM = vec3(0.0); // mie contribution
-    float opticalDepthM = 0.0;
+    // Cornette-Shanks
     float phaseM = 3.0 / (8.0 * PI) * ((1.0 - g * g) * (1.0 + mu * mu)) / ((2.0 + g * g) * pow(1.0 + g * g - 2.0 * g * mu, 1.5));
-    #endif
+    vec3 sum = vec3(0.0);
+    vec3 throughput = vec3(1.0);
     for (uint i = 0u; i < SAMPLES; ++i) {
-        vec3 samplePosition = orig + (tCurrent + segmentLength * 0.5) * dir;
-        float height = length(samplePosition) - Re;
-        // compute optical depth for light
-        float hr = exp(-height / Hr) * segmentLength;
-        opticalDepthR += hr;
-        #ifdef MIE
-        float hm = exp(-height / Hm) * segmentLength;
-        opticalDepthM += hm;
-        #endif
-        // light optical depth
-        float t0Light, t1Light;
-        raySphereIntersect(samplePosition, sunDirection, Ra, t0Light, t1Light);
-        float segmentLengthLight = t1Light / float(SAMPLES_LIGHT), tCurrentLight = 0.0;
-        float opticalDepthLightR = 0.0;
-        #ifdef MIE
-        float opticalDepthLightM = 0.0;
-        #endif
-        uint j;
-        for (j = 0u; j < SAMPLES_LIGHT; ++j) {
-            vec3 samplePositionLight = samplePosition + (tCurrentLight + segmentLengthLight * 0.5) * sunDirection;
-            float heightLight = length(samplePositionLight) - Re;
-            if (heightLight < 0.0) break;
-            opticalDepthLightR += exp(-heightLight / Hr) * segmentLengthLight;
-            #ifdef MIE
-            opticalDepthLightM += exp(-heightLight / Hm) * segmentLengthLight;
-            #endif
-            tCurrentLight += segmentLengthLight;
-        }
-        if (j == SAMPLES_LIGHT) {
-            #ifdef MIE
-            vec3 tau = betaR * (opticalDepthR + opticalDepthLightR) + betaM * MIE_EXTINCTION_MUL * (opticalDepthM + opticalDepthLightM);
-            #else
-            vec3 tau = betaR * (opticalDepthR + opticalDepthLightR);
-            #endif
-            vec3 attenuation = vec3(exp(-tau.x), exp(-tau.y), exp(-tau.z));
-            sumR += attenuation * hr;
-            #ifdef MIE
-            sumM += attenuation * hm;
-            #endif
-        }
-        tCurrent += segmentLength;
+        vec3 samplePosition = orig + (tmin + segmentLength * (float(i) + 0.5)) * dir;
+        float r = length(samplePosition);
+        float height = max(r - Re, 0.0);
+        vec3 scatterR = betaR * exp(-height / Hr);
+        vec3 scatterM = betaM * exp(-height / Hm);
+        vec3 extinction = max(scatterR + scatterM * MIE_EXTINCTION_MUL, vec3(1e-20));
+        float muSun = dot(samplePosition, sunDirection) / r;
+        vec3 scattered = transmittance(r, muSun) * (scatterR * phaseR + scatterM * phaseM)
+            + multipleScattering(r, muSun) * (scatterR + scatterM);
+        vec3 segmentTransmittance = exp(-extinction * segmentLength);
+        // Integrated over the segment rather than sampled, so it doesn't overshoot in thick air
+        sum += throughput * scattered * (1.0 - segmentTransmittance) / extinction;
+        throughput *= segmentTransmittance;
     }
-
-    #ifdef MIE
-    return vec4((sumR * betaR * phaseR + sumM * betaM * phaseM) * intensity, 1.0);
-    #else
-    return vec4((sumR * betaR * phaseR) * intensity, 1.0);
-    #endif
+    return vec4(sum * intensity, 1.0 - (throughput.r + throughput.g + throughput.b) / 3.0);
 }
 
 void main() {
+    // Interpolated per fragment, the vertex shader's value is only exact at vertices
+    // Only in the LOG_DEPTH variant, writing depth turns off early depth testing
+#ifdef LOG_DEPTH
+    gl_FragDepth = log2(flogz) * log_depth;
+#endif
     vec3 dir = normalize(FragPos * 1e6 - cam_pos);
 	float t0, t1, tMax = INFINITY;
     if (raySphereIntersect(pos, dir, Re, t0, t1) && t0 > 0.0) {
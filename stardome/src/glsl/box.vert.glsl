@@ -5,9 +5,11 @@ out vec3 TexCoords;
 
 uniform mat4 projection;
 uniform mat4 view;
+// Depth of the far plane in NDC, 0 with reversed-Z
+uniform float far_depth;
 
 void main() {
     TexCoords = aPos;
     vec4 pos = projection * view * vec4(aPos, 1.0);
-    gl_Position = pos.xyww; // Z value always 1
+    gl_Position = vec4(pos.xy, pos.w * far_depth, pos.w); // Always on the far plane
 }
@@ -0,0 +1,29 @@
+#version 300 es
+precision highp float;
+out vec4 FragColor;
+
+in vec3 FragPos;
+in vec3 Norm;
+in vec2 TexCoord;
+
+in float flogz;
+uniform float log_depth;
+// Cloud cover in the red channel
+uniform sampler2D clouds;
+uniform vec3 sun;
+
+// Same as the ground in planet.frag.glsl
+#define AMBIENT (0.0)
+
+void main() {
+    // Only in the LOG_DEPTH variant, writing depth turns off early depth testing
+#ifdef LOG_DEPTH
+    gl_FragDepth = log2(flogz) * log_depth;
+#endif
+    float cover = texture(clouds, TexCoord).r;
+    float light = 1.0;
+    if (sun != vec3(0.0, 0.0, 0.0)) {
+        light = AMBIENT + max(dot(normalize(Norm), normalize(sun)), 0.0);
+    }
+    FragColor = vec4(vec3(light), cover);
+}
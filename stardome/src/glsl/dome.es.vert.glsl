@@ -0,0 +1,8 @@
+#version 300 es
+out vec2 ndc;
+
+void main() {
+    // Single triangle covering the screen
+    ndc = vec2(float((gl_VertexID & 1) << 2) - 1.0, float((gl_VertexID & 2) << 1) - 1.0);
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
@@ -0,0 +1,25 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 ndc;
+
+// Faces rendered by Graphics::bind_dome_face
+uniform samplerCube faces;
+// Angle from the centre to the edge of the circle (radians)
+uniform float half_fov;
+uniform float aspect;
+
+// Azimuthal equidistant (dome master), the circle fits the shorter side
+void main() {
+    vec2 p = aspect > 1.0 ? vec2(ndc.x * aspect, ndc.y) : vec2(ndc.x, ndc.y / aspect);
+    float r = length(p);
+    if (r > 1.0) {
+        FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    float theta = r * half_fov;
+    float phi = atan(p.y, p.x);
+    // Camera looks down -Z
+    vec3 dir = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), -cos(theta));
+    FragColor = vec4(texture(faces, dir).rgb, 1.0);
+}
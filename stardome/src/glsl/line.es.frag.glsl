@@ -0,0 +1,63 @@
+#version 300 es
+precision highp float;
+out vec4 FragColor;
+
+
+flat in vec2 s0;
+flat in vec2 s1;
+flat in vec4 c0;
+flat in vec4 c1;
+flat in float d0;
+flat in float d1;
+in float flogz;
+uniform float log_depth;
+uniform vec4 color;
+uniform float width;
+// 0 solid, 1 dashed, 2 dotted
+uniform int style;
+// Dash and gap, or dot spacing (units along the line)
+uniform vec2 pattern;
+#ifdef TRAIL
+// Orbit trails, where distances are seconds from the same anchor as `now`, see OrbitPath
+uniform float now;
+uniform float past;
+uniform float future;
+uniform float fade;
+uniform float future_alpha;
+#endif
+
+void main() {
+    vec2 seg = s1 - s0;
+    float len2 = dot(seg, seg);
+    // Nearest point on the segment, the clamp gives round caps (and joins where segments meet)
+    float h = len2 > 0.0 ? clamp(dot(gl_FragCoord.xy - s0, seg) / len2, 0.0, 1.0) : 0.0;
+    float d = distance(gl_FragCoord.xy, s0 + h * seg);
+    float along = mix(d0, d1, h);
+    if (style == 1) {
+        if (mod(along, pattern.x + pattern.y) > pattern.x) {
+            discard;
+        }
+    } else if (style == 2) {
+        // Pixels per unit along this segment
+        float k = sqrt(len2) / max(abs(d1 - d0), 1e-9);
+        float u = mod(along, pattern.x);
+        d = length(vec2(min(u, pattern.x - u) * k, d));
+    }
+    float a = clamp(width * 0.5 + 0.5 - d, 0.0, 1.0);
+#ifdef TRAIL
+    float age = now - along;
+    // Samples run a little past the window, cut exactly here
+    if (age > past || -age > future) {
+        discard;
+    }
+    a *= age >= 0.0 ? mix(1.0, fade, age / max(past, 1e-6)) : future_alpha;
+#endif
+    if (a <= 0.0) {
+        discard;
+    }
+    // Only in the LOG_DEPTH variant, writing depth turns off early depth testing
+#ifdef LOG_DEPTH
+    gl_FragDepth = log2(flogz) * log_depth;
+#endif
+    FragColor = color * mix(c0, c1, h) * vec4(1.0, 1.0, 1.0, a);
+}
@@ -0,0 +1,86 @@
+#version 330 core
+// One instance per segment, drawn as a 4 vertex triangle strip
+layout (location = 0) in vec3 aPos0;
+layout (location = 1) in vec4 aColor0;
+layout (location = 2) in float aDist0;
+layout (location = 3) in vec3 aPos1;
+layout (location = 4) in vec4 aColor1;
+layout (location = 5) in float aDist1;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+// 1 / log2(far / near + 1), 0 when logarithmic depth is off
+uniform float log_depth;
+uniform float depth_near;
+// x, y, width, height (pixels)
+uniform vec4 viewport;
+uniform float width;
+// Depth of the far plane in NDC for points at infinity, negative otherwise
+uniform float far_depth;
+
+// Ends in window coordinates, compared with gl_FragCoord
+flat out vec2 s0;
+flat out vec2 s1;
+flat out vec4 c0;
+flat out vec4 c1;
+flat out float d0;
+flat out float d1;
+out float flogz;
+
+vec2 to_window(vec4 clip) {
+    return viewport.xy + (clip.xy / clip.w * 0.5 + 0.5) * viewport.zw;
+}
+
+void main() {
+    mat4 mvp = projection * view * model;
+    vec4 p0 = mvp * vec4(aPos0, 1.0);
+    vec4 p1 = mvp * vec4(aPos1, 1.0);
+    c0 = aColor0;
+    c1 = aColor1;
+    d0 = aDist0;
+    d1 = aDist1;
+
+    // Cut the segment where it passes behind the camera, so both ends project
+    // Orthographic w is always 1, directions at infinity only need to be in front
+    float near_w = projection[3][3] == 0.0 ? (far_depth < 0.0 ? depth_near * 0.5 : 1e-6) : 0.0;
+    if (p0.w < near_w && p1.w < near_w) {
+        gl_Position = vec4(0.0, 0.0, 2.0, 1.0);
+        return;
+    }
+    if (p0.w < near_w) {
+        float t = (near_w - p0.w) / (p1.w - p0.w);
+        p0 = mix(p0, p1, t);
+        c0 = mix(c0, c1, t);
+        d0 = mix(d0, d1, t);
+    } else if (p1.w < near_w) {
+        float t = (near_w - p1.w) / (p0.w - p1.w);
+        p1 = mix(p1, p0, t);
+        c1 = mix(c1, c0, t);
+        d1 = mix(d1, d0, t);
+    }
+
+    s0 = to_window(p0);
+    s1 = to_window(p1);
+    vec2 dir = s1 - s0;
+    dir = length(dir) > 1e-6 ? normalize(dir) : vec2(1.0, 0.0);
+    vec2 normal = vec2(-dir.y, dir.x);
+    // Room for the round caps and a pixel of antialiasing
+    float r = width * 0.5 + 1.0;
+    bool end = gl_VertexID >= 2;
+    float side = gl_VertexID % 2 == 0 ? -1.0 : 1.0;
+    vec4 p = end ? p1 : p0;
+    vec2 s = (end ? s1 : s0) + (side * normal + (end ? dir : -dir)) * r;
+
+    gl_Position = vec4(((s - viewport.xy) / viewport.zw * 2.0 - 1.0) * p.w, p.z, p.w);
+    flogz = 1.0 + p.w / depth_near;
+    if (far_depth >= 0.0) {
+        gl_Position.z = far_depth * p.w;
+#ifdef LOG_DEPTH
+        // So the fragment shader writes the far plane's depth too
+        flogz = exp2((far_depth * 0.5 + 0.5) / log_depth);
+#endif
+    } else if (log_depth > 0.0) {
+        gl_Position.z = (log2(max(1e-6, flogz)) * log_depth * 2.0 - 1.0) * p.w;
+    }
+}
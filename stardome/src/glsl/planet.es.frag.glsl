@@ -5,41 +5,137 @@ out vec4 FragColor;
 in vec3 FragPos;
 in vec3 Norm;
 in vec2 TexCoord;
+in vec3 ModelPos;
+in vec3 Tangent;
 
+in float flogz;
+uniform float log_depth;
 uniform sampler2D texture1;
+// Tangent space (east, north, up) in RGB
+uniform sampler2D normal_map;
+uniform bool has_normal_map;
+// Specular strength in red, roughness in green
+uniform sampler2D specular_map;
+uniform bool has_specular_map;
+// Shades the relief when there is no normal map, see planet.vert.glsl
+uniform sampler2D height_map;
+uniform vec2 height_range;
+// Emitted on the night side, such as city lights
+uniform sampler2D night_texture;
+// 0 without a night texture
+uniform float night_intensity;
+// Sine of the Sun's depression below the horizon where the night texture is fully on
+uniform float twilight;
 uniform vec3 sun;
 uniform vec3 cam_pos;
+// Cloud cover in the red channel, see clouds.frag.glsl
+uniform sampler2D cloud_texture;
+// Darkening under full cover, 0 without clouds
+uniform float cloud_shadow;
+// Radius of the cloud shell relative to the ground
+uniform float cloud_radius;
+// Drift of the clouds east of the ground (radians)
+uniform float cloud_angle;
+// Direction of the Sun in model space
+uniform vec3 sun_model;
 
+// Specular fades in over these ranges of diffuse light and texture alpha
 #define DIFF_THRSH (0.05)
 #define SPEC_THRSH (0.95)
+#define SPEC_FADE (0.05)
 #define SPEC_COEFF (1.0)
 #define AMBIENT (0.0)
+// Without a specular map
 #define SHININESS (4.0)
+// Roughness 0 and 1 of a specular map
+#define SHININESS_SMOOTH (128.0)
+#define SHININESS_ROUGH (2.0)
+
+#define PI (3.14159265358979)
+
+// Cover of the clouds between here and the Sun
+float cloud_cover() {
+    vec3 l = normalize(sun_model);
+    float b = dot(ModelPos, l);
+    // Inside the shell, so this always hits it
+    vec3 p = ModelPos + l * (sqrt(b * b - 1.0 + cloud_radius * cloud_radius) - b);
+    // Into the frame of the clouds, then the same mapping as Mesh::uv_sphere
+    float lon = atan(-p.y, -p.x) - cloud_angle;
+    float lat = asin(clamp(p.z / cloud_radius, -1.0, 1.0));
+    vec2 uv = vec2(fract(lon / (2.0 * PI)), lat / PI + 0.5);
+    return texture(cloud_texture, uv).r;
+}
+
+// Perturbed by the normal map, or the slope of the height map
+vec3 surface_normal(vec3 n) {
+    vec3 t = normalize(Tangent - n * dot(n, Tangent));
+    vec3 b = cross(n, t);
+    vec3 tn = vec3(0.0, 0.0, 1.0);
+    if (has_normal_map) {
+        tn = normalize(texture(normal_map, TexCoord).rgb * 2.0 - 1.0);
+    } else if (height_range != vec2(0.0, 0.0)) {
+        vec2 texel = 1.0 / vec2(textureSize(height_map, 0));
+        float dx = texture(height_map, TexCoord + vec2(texel.x, 0.0)).r
+            - texture(height_map, TexCoord - vec2(texel.x, 0.0)).r;
+        float dy = texture(height_map, TexCoord + vec2(0.0, texel.y)).r
+            - texture(height_map, TexCoord - vec2(0.0, texel.y)).r;
+        float span = height_range.y - height_range.x;
+        // Radii across two texels east and north
+        float cos_lat = max(length(ModelPos.xy), 1e-3);
+        float east = 4.0 * PI * texel.x * cos_lat;
+        float north = 2.0 * PI * texel.y;
+        tn = normalize(vec3(-dx * span / east, -dy * span / north, 1.0));
+    }
+    return normalize(t * tn.x + b * tn.y + n * tn.z);
+}
 
-// TODO Night lighting (cities)
 // TODO Moonlight
 void main() {
+    // Interpolated per fragment, the vertex shader's value is only exact at vertices
+    // Only in the LOG_DEPTH variant, writing depth turns off early depth testing
+#ifdef LOG_DEPTH
+    gl_FragDepth = log2(flogz) * log_depth;
+#endif
     vec4 data = texture(texture1, TexCoord);
     vec3 result = data.rgb;
 
     if (sun != vec3(0.0, 0.0, 0.0)) {
         // Diffuse
         vec3 light = normalize(sun);
-        vec3 normal = normalize(Norm);
-        float diffuse = max(dot(normal, light), 0.0);
-        float specular = 0.0;
-
-        // TODO fade into specular/diffuse (sharp edge looks bad)
-        // Potentially determine using ocean color as well as bathymetry
-        // TODO pick a nicer ocean color
-        if (data.a <= SPEC_THRSH && diffuse > DIFF_THRSH) {
-            // Specular
-            vec3 view = normalize(cam_pos - FragPos);
-            vec3 halfway = normalize(light + view);
-            specular = SPEC_COEFF * pow(max(dot(normal, halfway), 0.0), SHININESS);
+        // Sine of the Sun's altitude, without the relief so the terminator stays put
+        float elevation = dot(normalize(Norm), light);
+        vec3 normal = surface_normal(normalize(Norm));
+        float diffuse = max(dot(normal, light), 0.0) * step(0.0, elevation);
+
+        // Strength and shininess, from the map or else the water in the texture's alpha
+        float strength;
+        float shininess = SHININESS;
+        if (has_specular_map) {
+            vec2 sr = texture(specular_map, TexCoord).rg;
+            strength = sr.r;
+            shininess = mix(SHININESS_SMOOTH, SHININESS_ROUGH, sr.g);
+        } else {
+            // TODO pick a nicer ocean color
+            strength = 1.0 - smoothstep(SPEC_THRSH - SPEC_FADE, SPEC_THRSH, data.a);
+        }
+        strength *= smoothstep(0.0, DIFF_THRSH, diffuse);
+        vec3 view = normalize(cam_pos - FragPos);
+        vec3 halfway = normalize(light + view);
+        float specular = SPEC_COEFF * strength * pow(max(dot(normal, halfway), 0.0), shininess);
+
+        if (cloud_shadow > 0.0 && diffuse > 0.0) {
+            float shade = 1.0 - cloud_shadow * cloud_cover();
+            diffuse *= shade;
+            specular *= shade;
         }
 
         result *= vec3(AMBIENT + diffuse + specular);
+
+        // Lights come on from just before the terminator until the sky is dark
+        if (night_intensity > 0.0) {
+            float night = smoothstep(-0.01, twilight, -elevation);
+            result += texture(night_texture, TexCoord).rgb * night * night_intensity;
+        }
 	}
 
     FragColor = vec4(result, 1.0);
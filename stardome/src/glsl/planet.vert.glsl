@@ -2,19 +2,43 @@
 layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec3 aNorm;
 layout (location = 2) in vec2 aTexCoord;
+// East
+layout (location = 3) in vec3 aTangent;
 
 out vec3 FragPos;
 out vec3 Norm;
 out vec2 TexCoord;
+out vec3 Tangent;
+// On the unit sphere, for finding the cloud above
+out vec3 ModelPos;
 
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
+// 1 / log2(far / near + 1), 0 when logarithmic depth is off
+uniform float log_depth;
+uniform float depth_near;
+// Height in the red channel
+uniform sampler2D height_map;
+// Black and white of the height map as fractions of the radius, both 0 without one
+uniform vec2 height_range;
+
+out float flogz;
 
 void main() {
-    gl_Position = projection * view * model * vec4(aPos, 1.0);
-    FragPos = vec3(model * vec4(aPos, 1.0));
+    vec3 pos = aPos;
+    if (height_range != vec2(0.0, 0.0)) {
+        pos *= 1.0 + mix(height_range.x, height_range.y, textureLod(height_map, aTexCoord, 0.0).r);
+    }
+    gl_Position = projection * view * model * vec4(pos, 1.0);
+    flogz = 1.0 + gl_Position.w / depth_near;
+    if (log_depth > 0.0) {
+        gl_Position.z = (log2(max(1e-6, flogz)) * log_depth * 2.0 - 1.0) * gl_Position.w;
+    }
+    FragPos = vec3(model * vec4(pos, 1.0));
     // TODO this is very costly (see learnopengl)
     Norm = mat3(transpose(inverse(model))) * aNorm;
     TexCoord = aTexCoord;
+    Tangent = mat3(model) * aTangent;
+    ModelPos = aPos;
 }
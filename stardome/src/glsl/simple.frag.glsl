@@ -3,8 +3,15 @@ out vec4 FragColor;
 
 in vec4 gl_FragCoord;
 
+in float flogz;
+uniform float log_depth;
 uniform vec4 color;
 
 void main() {
+    // Interpolated per fragment, the vertex shader's value is only exact at vertices
+    // Only in the LOG_DEPTH variant, writing depth turns off early depth testing
+#ifdef LOG_DEPTH
+    gl_FragDepth = log2(flogz) * log_depth;
+#endif
 	FragColor = color;
 }
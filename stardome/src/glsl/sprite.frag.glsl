@@ -0,0 +1,40 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec4 gl_FragCoord;
+
+in vec2 uv;
+flat in vec4 vColor;
+flat in float radius;
+flat in float icon;
+in float flogz;
+uniform float log_depth;
+uniform vec4 color;
+// 0 square, 1 disc, 2 atlas icon
+uniform int shape;
+uniform sampler2D atlas;
+// Columns and rows of the atlas
+uniform vec2 grid;
+
+void main() {
+    vec4 c = color * vColor;
+    if (shape == 1) {
+        c.a *= clamp((1.0 - length(uv)) * radius + 0.5, 0.0, 1.0);
+    } else if (max(abs(uv.x), abs(uv.y)) > 1.0) {
+        discard;
+    }
+    if (shape == 2) {
+        // Cells are numbered from the top left, textures are stored bottom up
+        vec2 cell = vec2(mod(icon, grid.x), floor(icon / grid.x));
+        vec2 tc = uv * 0.5 + 0.5;
+        c *= texture(atlas, vec2((cell.x + tc.x) / grid.x, 1.0 - (cell.y + 1.0 - tc.y) / grid.y));
+    }
+    if (c.a <= 0.0) {
+        discard;
+    }
+    // Only in the LOG_DEPTH variant, writing depth turns off early depth testing
+#ifdef LOG_DEPTH
+    gl_FragDepth = log2(flogz) * log_depth;
+#endif
+    FragColor = c;
+}
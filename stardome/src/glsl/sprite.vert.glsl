@@ -0,0 +1,52 @@
+#version 330 core
+// One instance per point, drawn as a 4 vertex triangle strip
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec4 aColor;
+layout (location = 2) in float aSize;
+layout (location = 3) in float aIcon;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+// 1 / log2(far / near + 1), 0 when logarithmic depth is off
+uniform float log_depth;
+uniform float depth_near;
+// x, y, width, height (pixels)
+uniform vec4 viewport;
+uniform float width;
+// Depth of the far plane in NDC for points at infinity, negative otherwise
+uniform float far_depth;
+
+// -1 to 1 across the marker, beyond it in the antialiasing margin
+out vec2 uv;
+flat out vec4 vColor;
+flat out float radius;
+flat out float icon;
+out float flogz;
+
+void main() {
+    vec4 p = projection * view * model * vec4(aPos, 1.0);
+    if (p.w <= 0.0) {
+        gl_Position = vec4(0.0, 0.0, 2.0, 1.0);
+        return;
+    }
+    radius = aSize * width * 0.5;
+    // A pixel of room for antialiasing
+    float r = radius + 1.0;
+    vec2 corner = vec2(gl_VertexID % 2 == 0 ? -1.0 : 1.0, gl_VertexID >= 2 ? 1.0 : -1.0);
+    uv = corner * r / max(radius, 1e-6);
+    vColor = aColor;
+    icon = aIcon;
+
+    gl_Position = p + vec4(corner * r / viewport.zw * 2.0 * p.w, 0.0, 0.0);
+    flogz = 1.0 + p.w / depth_near;
+    if (far_depth >= 0.0) {
+        gl_Position.z = far_depth * p.w;
+#ifdef LOG_DEPTH
+        // So the fragment shader writes the far plane's depth too
+        flogz = exp2((far_depth * 0.5 + 0.5) / log_depth);
+#endif
+    } else if (log_depth > 0.0) {
+        gl_Position.z = (log2(max(1e-6, flogz)) * log_depth * 2.0 - 1.0) * p.w;
+    }
+}
@@ -0,0 +1,15 @@
+#version 300 es
+precision highp float;
+out vec4 FragColor;
+
+in vec2 uv;
+flat in vec4 vColor;
+flat in float radius;
+
+void main() {
+    float a = vColor.a * clamp((1.0 - length(uv)) * radius + 0.5, 0.0, 1.0);
+    if (a <= 0.0) {
+        discard;
+    }
+    FragColor = vec4(vColor.rgb, a);
+}
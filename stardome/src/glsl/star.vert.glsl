@@ -0,0 +1,41 @@
+#version 330 core
+// One instance per star, drawn as a 4 vertex triangle strip
+layout (location = 0) in vec3 aDir;
+layout (location = 1) in vec4 aColor;
+layout (location = 2) in float aMag;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+// Depth of the far plane in NDC, 0 with reversed-Z
+uniform float far_depth;
+// x, y, width, height (pixels)
+uniform vec4 viewport;
+uniform float limit;
+uniform float brightness;
+uniform float size;
+uniform float max_size;
+
+// -1 to 1 across the star, beyond it in the antialiasing margin
+out vec2 uv;
+flat out vec4 vColor;
+flat out float radius;
+
+void main() {
+    // Light relative to a star at the limit, alpha saturates first and then the size grows
+    float flux = brightness * pow(10.0, -0.4 * (aMag - limit));
+    radius = min(size * pow(max(flux, 1.0), 0.25), max_size);
+    vColor = vec4(aColor.rgb, min(flux, 1.0));
+
+    vec4 p = projection * view * model * vec4(aDir, 1.0);
+    if (p.w <= 0.0) {
+        gl_Position = vec4(0.0, 0.0, 2.0, 1.0);
+        return;
+    }
+    // A pixel of room for antialiasing
+    float r = radius + 1.0;
+    vec2 corner = vec2(gl_VertexID % 2 == 0 ? -1.0 : 1.0, gl_VertexID >= 2 ? 1.0 : -1.0);
+    uv = corner * r / radius;
+    p.xy += corner * r / viewport.zw * 2.0 * p.w;
+    gl_Position = vec4(p.xy, p.w * far_depth, p.w); // Always on the far plane
+}
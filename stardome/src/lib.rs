@@ -1,20 +1,52 @@
 #![allow(unused_variables, dead_code)]
 extern crate nalgebra as na;
+mod clock;
 mod gfx;
+mod source;
 
+#[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+pub use clock::ClockPanel;
+pub use clock::SimClock;
+pub use gfx::camera::{Camera, DepthMode, Projection};
+pub use gfx::constellations::{ConstellationBoundaries, ConstellationLines, ConstellationNames, Figure};
+pub use gfx::controller::{CameraController, CameraMode};
+pub use gfx::drawable::Atlas;
 pub use gfx::drawable::Atmosphere;
+pub use gfx::drawable::Clouds;
+pub use gfx::drawable::LineStyle;
+pub use gfx::drawable::Marker;
+pub use gfx::drawable::Pass;
 pub use gfx::drawable::Planet;
 pub use gfx::drawable::Points;
+pub use gfx::drawable::Relief;
 pub use gfx::drawable::Text;
+pub use gfx::drawable::Topology;
+pub use gfx::grid::{Grid, GridFrame};
+pub use gfx::orbit::{Elements, OrbitPath, Trajectory};
+pub use gfx::scene::{Handle, NodeId, Scene};
+pub use gfx::stars::{CatalogFormat, Star, StarField};
+pub use gfx::terrain::{Elevation, Terrain, TileLayout, TileSource};
 pub use gfx::texture::Texture;
+pub use source::{BodySource, Ephemeris, EphemerisSource};
 
 pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 pub type BoxResult<T> = Result<T, BoxError>;
 
+/// Kilometres per world unit, positions and radii are in thousands of km
+pub const KM_PER_UNIT: f64 = 1000.0;
+
 pub struct StarDome {
     pub graphics: gfx::Graphics,
     pub cam: gfx::camera::Camera,
     pub sun: na::Vector3<f64>,
+    /// Drawn every frame by `frame`
+    pub scene: gfx::scene::Scene,
+    /// Advanced by `frame`, drawables are updated with its epoch
+    pub clock: clock::SimClock,
+    #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+    pub clock_panel: clock::ClockPanel,
+    /// Mouse and keyboard control of `cam`, `None` to leave it to the user
+    pub controller: Option<gfx::controller::CameraController>,
     begin: std::time::Instant,
     frame_t: std::time::Instant,
     #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
@@ -51,6 +83,14 @@ impl StarDome {
                 90.0_f32.to_radians(),
             ),
             sun: na::Vector3::zeros(), // When it's zeros lighting is disabled
+            scene: gfx::scene::Scene::default(),
+            clock: clock::SimClock::default(),
+            #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+            clock_panel: clock::ClockPanel::default(),
+            controller: Some(gfx::controller::CameraController::new(
+                gfx::controller::CameraMode::Orbit,
+                na::Vector3::zeros(),
+            )),
             begin: t,
             frame_t: t,
             #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
@@ -71,15 +111,112 @@ impl StarDome {
         na::convert(self.sun.normalize())
     }
 
+    /// Animate the camera to `position` facing `target`, which the controller then orbits
+    pub fn fly_to(&mut self, position: na::Vector3<f64>, target: na::Vector3<f64>, duration: f32) {
+        self.cam.fly_to(position, target, duration);
+        if let Some(c) = &mut self.controller {
+            c.set_target(target);
+        }
+    }
+
+    /// Draw immediately in all of its passes, before anything in `scene`
     pub fn draw<T: gfx::drawable::Drawable>(&mut self, d: &mut T) {
+        self.draw_transformed(d, &na::Matrix4::identity());
+    }
+
+    /// Draw immediately in the frame given by `tf`
+    pub fn draw_transformed<T: gfx::drawable::Drawable + ?Sized>(
+        &mut self,
+        d: &mut T,
+        tf: &na::Matrix4<f64>,
+    ) {
+        d.update(self.clock.epoch());
+        d.update_view(&mut self.graphics, &self.cam, tf);
+        for pass in gfx::drawable::Pass::ALL.iter() {
+            if d.passes().contains(pass) {
+                self.draw_pass(d, tf, *pass);
+            }
+        }
+    }
+
+    fn draw_pass<T: gfx::drawable::Drawable + ?Sized>(
+        &mut self,
+        d: &mut T,
+        tf: &na::Matrix4<f64>,
+        pass: gfx::drawable::Pass,
+    ) {
         let s = self.get_sun_dir();
-        println!("Drawing something");
-        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
-        d.draw(&mut self.graphics, &self.cam, s, &mut self.text)
-            .unwrap();
-        #[cfg(any(target_os = "emscripten", feature = "gles"))]
-        d.draw(&mut self.graphics, &self.cam, s).unwrap();
-        println!("Drawn");
+        self.graphics.prepare_depth(&self.cam);
+        if let gfx::camera::Projection::Fisheye { fov } = self.cam.projection {
+            // Labels are only collected once, from the front face which is always in view
+            #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+            let mut scratch = Vec::new();
+            for n in (0..6).filter(|&n| gfx::dome::Dome::face_visible(n, fov)) {
+                let face = self.cam.dome_face(n);
+                self.graphics.bind_dome_face(n);
+                #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+                d.draw(
+                    &mut self.graphics,
+                    &face,
+                    tf,
+                    pass,
+                    s,
+                    if n == 5 { &mut self.text } else { &mut scratch },
+                )
+                .unwrap();
+                #[cfg(any(target_os = "emscripten", feature = "gles"))]
+                d.draw(&mut self.graphics, &face, tf, pass, s).unwrap();
+            }
+            self.graphics.unbind_dome();
+        } else {
+            #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+            d.draw(&mut self.graphics, &self.cam, tf, pass, s, &mut self.text)
+                .unwrap();
+            #[cfg(any(target_os = "emscripten", feature = "gles"))]
+            d.draw(&mut self.graphics, &self.cam, tf, pass, s).unwrap();
+        }
+    }
+
+    // Everything in the scene by pass, with the skybox after opaque geometry
+    fn draw_scene(&mut self) {
+        use gfx::drawable::Pass;
+        self.scene.update_drawables(self.clock.epoch());
+        self.scene.update();
+        self.scene.update_views(&mut self.graphics, &self.cam);
+        // Taken so drawables can be borrowed alongside self
+        let mut scene = std::mem::take(&mut self.scene);
+        for pass in Pass::ALL.iter() {
+            for k in scene.queue(*pass, &self.cam.position) {
+                if let Some((tf, d)) = scene.entry_mut(k) {
+                    self.draw_pass(d, tf, *pass);
+                }
+            }
+            if *pass == Pass::Opaque {
+                self.draw_skybox();
+            }
+        }
+        self.scene = scene;
+        if let gfx::camera::Projection::Fisheye { fov } = self.cam.projection {
+            self.graphics.draw_dome(fov);
+        }
+    }
+
+    fn draw_skybox(&mut self) {
+        match self.cam.projection {
+            // Diagrams have a plain background
+            gfx::camera::Projection::Orthographic { .. } => {}
+            gfx::camera::Projection::Fisheye { fov } => {
+                for n in (0..6).filter(|&n| gfx::dome::Dome::face_visible(n, fov)) {
+                    let face = self.cam.dome_face(n);
+                    self.graphics.bind_dome_face(n);
+                    self.graphics.draw_skybox(&face, &self.sun);
+                }
+                self.graphics.unbind_dome();
+            }
+            gfx::camera::Projection::Perspective => {
+                self.graphics.draw_skybox(&self.cam, &self.sun)
+            }
+        }
     }
 
     pub fn frame<F>(&mut self, mut f: F) -> BoxResult<std::time::Duration>
@@ -101,11 +238,27 @@ impl StarDome {
             if self.graphics.handle_event(&e) {
                 continue;
             }
+            if let Some(c) = &mut self.controller {
+                if c.handle_event(&e) {
+                    continue;
+                }
+            }
             if let sdl2::event::Event::Quit { .. } = e {
                 return Err("Quitting".into());
             }
         }
 
+        self.clock.advance(elapsed.as_secs_f64());
+
+        // The controller would fight an animated move
+        if !self.cam.animate(elapsed.as_secs_f32()) {
+            if let Some(c) = &mut self.controller {
+                c.update(&mut self.cam, elapsed.as_secs_f64());
+            }
+        }
+
+        self.draw_scene();
+
         #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
         self.imgui_sdl.prepare_frame(
             self.imgui.io_mut(),
@@ -121,10 +274,12 @@ impl StarDome {
         #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
         f(&mut ui);
         #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
+        self.clock.panel(&ui, &mut self.clock_panel);
+        #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
         if !self.text.is_empty() {
             let vc = std::mem::take(&mut self.text);
-            let tf =
-                self.cam.projection_matrix(self.graphics.aspect_ratio()) * self.cam.view_matrix();
+            let aspect_ratio = self.graphics.aspect_ratio();
+            let cam = &self.cam;
             let (sx, sy) = self.graphics.libs.window.size();
             let sx = sx as f32;
             let sy = sy as f32;
@@ -145,12 +300,11 @@ impl StarDome {
                 .build(&ui, || {
                     let no_pad = ui.push_style_var(imgui::StyleVar::WindowPadding([0.0, 0.0]));
                     for (pos, color, text) in vc.iter() {
-                        let pce =
-                            tf * na::Vector4::new(pos.x as f32, pos.y as f32, pos.z as f32, 1.0);
-                        let pos: [f32; 2] = [
-                            (pce.x / pce.w / 2.0 + 0.5) * sx,
-                            (-pce.y / pce.w / 2.0 + 0.5) * sy,
-                        ];
+                        let ndc = match cam.project(pos, aspect_ratio) {
+                            Some(p) => p,
+                            None => continue,
+                        };
+                        let pos: [f32; 2] = [(ndc.x / 2.0 + 0.5) * sx, (-ndc.y / 2.0 + 0.5) * sy];
                         let comp: [u8; 4] = unsafe { std::mem::transmute(*color) };
                         let col = [
                             comp[3] as f32 / 255.0,
@@ -162,12 +316,7 @@ impl StarDome {
                         // Clip offscreen based on text width
                         let [tx, ty] = ui.calc_text_size(text, false, 0.0);
 
-                        if pce.z > 0.0
-                            && pos[0] + tx > 0.0
-                            && pos[1] + ty > 0.0
-                            && pos[0] < sx
-                            && pos[1] < sy
-                        {
+                        if pos[0] + tx > 0.0 && pos[1] + ty > 0.0 && pos[0] < sx && pos[1] < sy {
                             ui.set_cursor_screen_pos(pos);
                             ui.text_colored(col, text);
                         }
@@ -176,15 +325,12 @@ impl StarDome {
                 });
         }
 
-        //self.graphics
-        //    .frame(&self.cam, self.begin.elapsed().as_secs_f32())?;
-        self.graphics.draw_skybox(&self.cam, &self.sun);
         #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
         self.imgui_sdl
             .prepare_render(&ui, &self.graphics.libs.window);
         #[cfg(all(not(target_os = "emscripten"), not(feature = "gles")))]
         self.imgui_gl.render(ui);
-        self.graphics.handle_frame();
+        self.graphics.handle_frame(&self.cam);
         Ok(self.frame_t.elapsed())
     }
 }
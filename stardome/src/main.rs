@@ -18,37 +18,36 @@ extern "C" {
 pub struct State {
     pub win: bool,
     pub sd: StarDome,
-    pub earth: stardome::Planet,
-    pub iss_label: stardome::Text,
     pub sun_line: stardome::Points,
 }
 
 pub fn main() {
     let mut sd = StarDome::new().unwrap();
 
-    let earth = stardome::Planet {
-        r_equatorial: 6.3781,
-        r_polar: 6.3568,
-        lighting: true,
-        texture: stardome::Texture::open("img/gen/earth.png").unwrap(),
-        atm: Some(stardome::Atmosphere {
-            offset: 0.06,
-            sun_intensity: 20.0,
-            scale_height_r: 7994.0,
-            scatter_coeff_r: na::Vector3::new(3.8e-6, 13.5e-6, 33.1e-6),
-            scale_height_m: 1200.0,
-            scatter_coeff_m: na::Vector3::from_element(21e-6),
-            asymmetry_m: 0.76,
-        }),
-        clouds: None,
-        tf: na::Matrix4::identity(),
-    };
+    // Falls back to the analytic ephemeris without a JPLEPH file
+    let eph = stardome::Ephemeris::load();
+    let mut earth = stardome::Planet::new(
+        &sputils::bodies::EARTH,
+        stardome::Texture::open("img/gen/earth.png").unwrap(),
+    )
+    .with_source(stardome::EphemerisSource::new(
+        &eph,
+        sputils::eph::Body::Earth,
+        sputils::eph::Body::Earth,
+    ));
+    if let Ok(t) = stardome::Texture::open("img/gen/earth_night.png") {
+        earth = earth.with_night(t);
+    }
+    earth.clouds = stardome::Texture::open("img/gen/earth_clouds.png")
+        .ok()
+        .map(stardome::Clouds::new);
+    sd.scene.insert(earth);
 
-    let iss_label = stardome::Text {
+    sd.scene.insert(stardome::Text {
         position: na::Vector3::zeros(),
         color: 0xFF00FF80,
         text: "International Space Station".to_string(),
-    };
+    });
 
     let mut sun_line = stardome::Points::new(0xFF8000FF, 4.0, true, vec![na::Vector3::zeros(); 2]);
     sd.sun = na::Vector3::new(149597.87, 0., 0.);
@@ -59,8 +58,6 @@ pub fn main() {
     let mut state = State {
         win: true,
         sd,
-        earth,
-        iss_label,
         sun_line,
     };
 
@@ -82,20 +79,15 @@ pub fn main() {
 #[allow(clippy::missing_safety_doc)]
 pub unsafe extern "C" fn loop_stuff(state: *mut std::ffi::c_void) {
     let state = &mut *(state as *mut State);
-    state.sd.draw(&mut state.earth);
-    state.sd.draw(&mut state.iss_label);
-    //state.sd.draw(&mut state.sun_line);
-
-    let mut rx = state.sd.cam.rx;
-    let mut ry = state.sd.cam.ry;
-    let mut rz = state.sd.cam.rz;
+    let (mut rx, mut ry, mut rz) = state.sd.cam.angles();
     let mut pos = [
-        state.sd.cam.position.x,
-        state.sd.cam.position.y,
-        state.sd.cam.position.z,
+        state.sd.cam.position.x as f32,
+        state.sd.cam.position.y as f32,
+        state.sd.cam.position.z as f32,
     ];
     let mut fov = state.sd.cam.get_fov().to_radians();
     let mut win = state.win;
+    let mut changed = false;
 
     #[allow(clippy::blocks_in_if_conditions)]
     if state
@@ -107,15 +99,15 @@ pub unsafe extern "C" fn loop_stuff(state: *mut std::ffi::c_void) {
             imgui::Window::new(im_str!("Camera"))
                 .size([420.0, 250.0], imgui::Condition::FirstUseEver)
                 .build(&ui, || {
-                    imgui::InputFloat3::new(&ui, im_str!("Position"), &mut pos).build();
+                    changed |= imgui::InputFloat3::new(&ui, im_str!("Position"), &mut pos).build();
                     ui.separator();
-                    imgui::AngleSlider::new(im_str!("RX"))
+                    changed |= imgui::AngleSlider::new(im_str!("RX"))
                         .min_degrees(0.0)
                         .build(&ui, &mut rx);
-                    imgui::AngleSlider::new(im_str!("RY"))
+                    changed |= imgui::AngleSlider::new(im_str!("RY"))
                         .min_degrees(0.0)
                         .build(&ui, &mut ry);
-                    imgui::AngleSlider::new(im_str!("RZ"))
+                    changed |= imgui::AngleSlider::new(im_str!("RZ"))
                         .min_degrees(0.0)
                         .build(&ui, &mut rz);
                     ui.separator();
@@ -132,11 +124,11 @@ pub unsafe extern "C" fn loop_stuff(state: *mut std::ffi::c_void) {
         emscripten_cancel_main_loop();
     }
     state.win = win;
-    state.sd.cam.position.x = pos[0];
-    state.sd.cam.position.y = pos[1];
-    state.sd.cam.position.z = pos[2];
-    state.sd.cam.rx = rx;
-    state.sd.cam.ry = ry;
-    state.sd.cam.rz = rz;
+    if changed {
+        state.sd.cam.position.x = pos[0] as f64;
+        state.sd.cam.position.y = pos[1] as f64;
+        state.sd.cam.position.z = pos[2] as f64;
+        state.sd.cam.set_angles(rx, ry, rz);
+    }
     state.sd.cam.set_fov(fov.to_degrees());
 }
@@ -0,0 +1,93 @@
+// Where a Planet is at a given epoch, so it can move itself as the clock runs
+use sputils::eph::{Body, JPL};
+use sputils::time::TDB;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Gives `Planet::tf` from the simulation epoch, called by `Drawable::update`
+pub trait BodySource {
+    /// Rotation and translation (in units) for `Planet::tf`, `None` leaves it as it is
+    fn transform(&mut self, epoch: TDB) -> Option<na::Matrix4<f64>>;
+}
+
+impl<F: FnMut(TDB) -> na::Matrix4<f64>> BodySource for F {
+    fn transform(&mut self, epoch: TDB) -> Option<na::Matrix4<f64>> {
+        Some(self(epoch))
+    }
+}
+
+/// Positions of the major bodies, cheap to clone and share between sources
+#[derive(Clone)]
+pub enum Ephemeris {
+    /// JPL DE file
+    Jpl(Rc<RefCell<JPL>>),
+    /// `sputils::analytic`, good to arcminutes for the planets and a fraction of a degree
+    /// for the Moon, no Pluto
+    Analytic,
+}
+
+impl Ephemeris {
+    /// The JPL ephemeris named by `JPLEPH`, or the analytic one if it can't be opened
+    pub fn load() -> Self {
+        match JPL::new() {
+            Ok(j) => Self::Jpl(Rc::new(RefCell::new(j))),
+            Err(_) => Self::Analytic,
+        }
+    }
+
+    pub fn is_analytic(&self) -> bool {
+        matches!(self, Self::Analytic)
+    }
+
+    /// Position (km) of `target` relative to `center`, ICRF axes.
+    ///
+    /// `None` outside a JPL file's span, or for Pluto without one
+    pub fn position(&self, t: TDB, target: Body, center: Body) -> Option<na::Vector3<f64>> {
+        match self {
+            Self::Jpl(j) => j.borrow_mut().position(t, target, center).ok(),
+            Self::Analytic => sputils::analytic::position(t, target, center),
+        }
+    }
+}
+
+impl Default for Ephemeris {
+    fn default() -> Self {
+        Self::load()
+    }
+}
+
+/// Body positioned by an ephemeris and oriented by its IAU rotation model
+pub struct EphemerisSource {
+    pub eph: Ephemeris,
+    pub body: Body,
+    /// Body at the origin of the frame the planet is drawn in
+    pub center: Body,
+    /// Without this only the position changes
+    pub rotate: bool,
+}
+
+impl EphemerisSource {
+    pub fn new(eph: &Ephemeris, body: Body, center: Body) -> Self {
+        Self {
+            eph: eph.clone(),
+            body,
+            center,
+            rotate: true,
+        }
+    }
+}
+
+impl BodySource for EphemerisSource {
+    fn transform(&mut self, epoch: TDB) -> Option<na::Matrix4<f64>> {
+        let p = if self.body == self.center {
+            na::Vector3::zeros()
+        } else {
+            self.eph.position(epoch, self.body, self.center)? / crate::KM_PER_UNIT
+        };
+        let r = match sputils::orient::body(self.body, epoch) {
+            Some(a) if self.rotate => sputils::orient::homogeneous(&a.matrix()),
+            _ => na::Matrix4::identity(),
+        };
+        Some(r.append_translation(&p))
+    }
+}